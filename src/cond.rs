@@ -0,0 +1,278 @@
+use std::{fmt, mem::discriminant};
+use crate::expr::{Expr, Var};
+use crate::types::{FloatMatch, ScanMode, ValueType};
+
+/// One scan mode with its parsed input.
+#[derive(Debug,Clone)]
+pub struct Atom{pub mode:ScanMode,pub val:Option<ValueType>,pub hi:Option<ValueType>,pub fm:FloatMatch}
+impl Atom{
+    /// Parses `value` as `mode`'s input. Modes without one still keep a parseable value, which picks the
+    /// types a first scan looks at.
+    pub fn new(mode:ScanMode,value:&str,fm:FloatMatch)->Result<Self,String>{
+        if mode==ScanMode::Between{
+            let (lo,hi)=ValueType::parse_user_range(value).ok_or("Between expects lo..hi")?;
+            return Ok(Self{mode,val:Some(lo),hi:Some(hi),fm});
+        }
+        let val=ValueType::parse_user_value(value);
+        if val.is_none()&&mode.takes_input(){return Err(format!("Failed to parse value {:?}",value.trim()));}
+        Ok(Self{mode,val,hi:None,fm:fm.for_input(value)})
+    }
+    /// An XOR value with an unknown key matches anything on its own and is only found by how it changes.
+    fn needs_old(&self)->bool{self.mode.needs_old()||matches!(self.val,Some(ValueType::Xor{key:None,..}))}
+    #[inline] fn test(&self,old:&ValueType,new:&ValueType)->bool{ValueType::comparator(self.mode,old,new,self.val.as_ref(),self.hi.as_ref(),self.fm)}
+    /// `test` for text, patterns, groups and unknown XOR keys of type `ty`, on raw bytes. Text is matched
+    /// on its encoded bytes, so invalid sequences in memory never decode into a false match; groups compare
+    /// their span. Whatever the key, the stored bytes of an XOR value change by the XOR of the last known
+    /// value in `ty` and the input.
+    fn test_raw(&self,ty:&ValueType,old:&[u8],new:&[u8])->bool{
+        match self.mode{
+            ScanMode::Exact|ScanMode::NotEqual=>{
+                let eq=self.val.as_ref().is_some_and(|v|discriminant(ty)==discriminant(v)&&match(ty,v){
+                    (ValueType::Str{enc,..},ValueType::Str{text,enc:e,nocase})=>e==enc&&enc.eq(*nocase,new,&enc.encode(text)),
+                    (ValueType::Xor{v:last,key:None},ValueType::Xor{v:want,key:None})=>{
+                        let (a,b)=(last.to_bytes(),want.to_bytes());
+                        a.len()==b.len()&&a.len()==new.len()&&old.iter().zip(new).zip(a.iter().zip(&b)).all(|((o,n),(a,b))|o^n==a^b)
+                    }
+                    _=>v.matches_raw(new,self.fm),
+                });
+                eq==(self.mode==ScanMode::Exact)
+            }
+            ScanMode::Changed=>old!=new,ScanMode::Unchanged=>old==new,ScanMode::Unknown=>true,
+            _=>false,
+        }
+    }
+}
+impl fmt::Display for Atom{
+    fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
+        match (&self.val,&self.hi){
+            (Some(lo),Some(hi))=>write!(f,"{} {lo}..{hi}",self.mode.name()),
+            (Some(v),None) if self.mode.takes_input()=>write!(f,"{} {v}",self.mode.name()),
+            _=>write!(f,"{}",self.mode.name()),
+        }
+    }
+}
+
+/// A scan condition: one mode or expression, or several combined with AND/OR/NOT and evaluated in one pass.
+#[derive(Debug,Clone)]
+pub enum Cond{Atom(Atom),Expr(Expr),And(Vec<Cond>),Or(Vec<Cond>),Not(Box<Cond>)}
+impl Cond{
+    pub fn mode(mode:ScanMode,value:&str,fm:FloatMatch)->Result<Self,String>{Atom::new(mode,value,fm).map(Cond::Atom)}
+    /// Modes and expressions, left to right.
+    fn leaves(&self)->Vec<&Cond>{
+        match self{Self::Atom(_)|Self::Expr(_)=>vec![self],Self::And(v)|Self::Or(v)=>v.iter().flat_map(Cond::leaves).collect(),Self::Not(c)=>c.leaves()}
+    }
+    fn atoms(&self)->impl Iterator<Item=&Atom>{self.leaves().into_iter().filter_map(|c|match c{Self::Atom(a)=>Some(a),_=>None})}
+    fn exprs(&self)->impl Iterator<Item=&Expr>{self.leaves().into_iter().filter_map(|c|match c{Self::Expr(e)=>Some(e),_=>None})}
+    fn eval(&self,f:&impl Fn(&Cond)->bool)->bool{
+        match self{Self::Atom(_)|Self::Expr(_)=>f(self),Self::And(v)=>v.iter().all(|c|c.eval(f)),Self::Or(v)=>v.iter().any(|c|c.eval(f)),Self::Not(c)=>!c.eval(f)}
+    }
+    /// Whether any part compares against an earlier value, so a first scan can only take a snapshot.
+    pub fn needs_old(&self)->bool{self.atoms().any(Atom::needs_old)||self.exprs().any(|e|e.uses(Var::Old)||e.uses(Var::First))}
+    /// Whether an expression reads the value of the first scan.
+    pub fn needs_first(&self)->bool{self.exprs().any(|e|e.uses(Var::First))}
+    pub fn is_unknown(&self)->bool{matches!(self,Self::Atom(a) if a.mode==ScanMode::Unknown)}
+    /// Input whose type picks the default scan types: the first one given, the wider bound for ranges.
+    pub fn hint(&self)->Option<&ValueType>{
+        self.leaves().into_iter().find_map(|c|match c{
+            Self::Atom(Atom{val:Some(l),hi:Some(h),..}) if ValueType::type_size(h)>ValueType::type_size(l)=>Some(h),
+            Self::Atom(a)=>a.val.as_ref(),
+            Self::Expr(e)=>e.input(),
+            _=>None,
+        })
+    }
+    /// The input of a lone `Exact` condition.
+    pub fn exact(&self)->Option<&ValueType>{match self{Self::Atom(a) if a.mode==ScanMode::Exact=>a.val.as_ref(),_=>None}}
+    /// Tests the raw bytes of a value of type `ty` at `addr`, decoded once for every part of the condition.
+    /// `first` is the value in the first scan, for expressions that read it.
+    pub fn test_raw(&self,ty:&ValueType,addr:usize,old:&[u8],new:&[u8],first:Option<&[u8]>)->bool{
+        if ty.is_bytes(){return self.eval(&|c|matches!(c,Self::Atom(a) if a.test_raw(ty,old,new)));}
+        let dec=|b:&[u8]|ValueType::from_bytes(b.to_vec(),ty.clone());
+        let (old,new,first)=(dec(old),dec(new),first.map(dec));
+        self.eval(&|c|match c{Self::Atom(a)=>a.test(&old,&new),Self::Expr(e)=>e.test(&new,&old,first.as_ref(),addr),_=>false})
+    }
+
+    /// Parses `changed AND > 100 AND < 5000`. Terms are mode names (`increased by 5`, `unknown`),
+    /// comparisons (`=`, `!=`, `>`, `>=`, `<`, `<=`) or a bare value for `Exact`, joined by `AND`/`&&`,
+    /// `OR`/`||` and `NOT`/`!` with parentheses; AND binds tighter than OR.
+    pub fn parse(input:&str,fm:FloatMatch)->Result<Self,String>{
+        let toks=tokenize(input);
+        let mut p=Parser{toks:&toks,at:0,depth:0,fm};
+        let c=p.or()?;
+        match p.toks.get(p.at){None=>Ok(c),Some(t)=>Err(format!("Unexpected {t:?}"))}
+    }
+}
+impl fmt::Display for Cond{
+    fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
+        let join=|f:&mut fmt::Formatter,v:&[Cond],op:&str|->fmt::Result{
+            for (i,c) in v.iter().enumerate(){
+                if i>0{write!(f," {op} ")?;}
+                if matches!(c,Cond::And(_)|Cond::Or(_)){write!(f,"({c})")?;}else{write!(f,"{c}")?;}
+            }
+            Ok(())
+        };
+        match self{
+            Self::Atom(a)=>write!(f,"{a}"),Self::Expr(e)=>write!(f,"{e}"),
+            Self::And(v)=>join(f,v,"AND"),Self::Or(v)=>join(f,v,"OR"),
+            Self::Not(c)=>if matches!(**c,Cond::Atom(_)|Cond::Not(_)){write!(f,"NOT {c}")}else{write!(f,"NOT ({c})")},
+        }
+    }
+}
+
+/// Deepest nesting of parentheses and NOT the parser recurses into.
+const MAX_DEPTH:usize=64;
+const OPS:&[&str]=&[">=","<=","!=","==",">","<","="];
+
+/// Splits on whitespace and parentheses, and splits a leading `!` or comparison off its value (`!<3`).
+fn tokenize(s:&str)->Vec<String>{
+    let mut v=vec![];
+    for mut w in s.replace('('," ( ").replace(')'," ) ").split_whitespace(){
+        while w.len()>1&&w.starts_with('!')&&!w.starts_with("!="){v.push("!".to_string()); w=&w[1..];}
+        match OPS.iter().find(|o|w.starts_with(**o)){
+            Some(o) if w.len()>o.len()=>{v.push(o.to_string()); v.push(w[o.len()..].to_string());}
+            _=>v.push(w.to_string()),
+        }
+    }
+    v
+}
+
+struct Parser<'a>{toks:&'a [String],at:usize,depth:usize,fm:FloatMatch}
+impl Parser<'_>{
+    fn peek(&self)->Option<&str>{self.toks.get(self.at).map(String::as_str)}
+    fn eat(&mut self,any:&[&str])->bool{
+        let hit=self.peek().is_some_and(|t|any.iter().any(|a|t.eq_ignore_ascii_case(a)));
+        if hit{self.at+=1;} hit
+    }
+    fn or(&mut self)->Result<Cond,String>{
+        let mut v=vec![self.and()?];
+        while self.eat(&["or","||"]){v.push(self.and()?);}
+        Ok(if v.len()==1{v.pop().unwrap()}else{Cond::Or(v)})
+    }
+    fn and(&mut self)->Result<Cond,String>{
+        let mut v=vec![self.unary()?];
+        while self.eat(&["and","&&"]){v.push(self.unary()?);}
+        Ok(if v.len()==1{v.pop().unwrap()}else{Cond::And(v)})
+    }
+    fn unary(&mut self)->Result<Cond,String>{
+        if self.depth>=MAX_DEPTH{return Err("Condition nested too deep".into());}
+        self.depth+=1; let c=self.prefix(); self.depth-=1; c
+    }
+    fn prefix(&mut self)->Result<Cond,String>{
+        // "not equal" is the NotEqual mode, not a negation
+        let ne=self.toks.get(self.at+1).is_some_and(|t|t.eq_ignore_ascii_case("equal"));
+        if !ne&&self.eat(&["not","!"]){return Ok(Cond::Not(Box::new(self.unary()?)));}
+        if self.eat(&["("]){
+            let c=self.or()?;
+            return if self.eat(&[")"]){Ok(c)}else{Err("Missing )".into())};
+        }
+        self.term()
+    }
+    /// Tokens up to the next operator or parenthesis, joined back into one value.
+    fn value(&mut self)->String{
+        let s=self.at;
+        while self.peek().is_some_and(|t|![")","(","and","&&","or","||"].iter().any(|k|t.eq_ignore_ascii_case(k))){self.at+=1;}
+        self.toks[s..self.at].join(" ")
+    }
+    fn term(&mut self)->Result<Cond,String>{
+        let Some(t)=self.peek() else{return Err("Expected a condition".into())};
+        if let Some(op)=OPS.iter().find(|o|**o==t){
+            self.at+=1;
+            let v=self.value();
+            let m=|mode|Cond::mode(mode,&v,self.fm);
+            return match *op{
+                ">"=>m(ScanMode::Greater),"<"=>m(ScanMode::Less),"!="=>m(ScanMode::NotEqual),
+                ">="=>Ok(Cond::Or(vec![m(ScanMode::Greater)?,m(ScanMode::Exact)?])),
+                "<="=>Ok(Cond::Or(vec![m(ScanMode::Less)?,m(ScanMode::Exact)?])),
+                _=>m(ScanMode::Exact),
+            };
+        }
+        // longest mode name spelled by the next tokens; "unknown" alone also names Unknown Initial Value
+        let mut names:Vec<(ScanMode,String)>=ScanMode::ALL.iter().map(|m|(*m,m.name().to_string())).collect();
+        names.push((ScanMode::Unknown,"Unknown".into()));
+        names.sort_by_key(|n|std::cmp::Reverse(n.1.split(' ').count()));
+        let found=names.into_iter().find(|(_,n)|{
+            let w:Vec<&str>=n.split(' ').collect();
+            self.toks.len()>=self.at+w.len()&&w.iter().zip(&self.toks[self.at..]).all(|(a,b)|a.eq_ignore_ascii_case(b))
+        });
+        match found{
+            Some((mode,n))=>{
+                self.at+=n.split(' ').count();
+                let v=if mode.takes_input(){self.value()}else{String::new()};
+                Cond::mode(mode,&v,self.fm)
+            }
+            None=>{let v=self.value(); Cond::mode(ScanMode::Exact,&v,self.fm)}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    fn parse(s:&str)->Cond{Cond::parse(s,FloatMatch::Epsilon).unwrap()}
+    fn test(c:&Cond,old:i32,new:i32)->bool{c.test_raw(&ValueType::Int32(0),0,&old.to_ne_bytes(),&new.to_ne_bytes(),None)}
+
+    #[test]
+    fn precedence(){
+        let c=parse("changed AND > 100 OR < 5");
+        assert_eq!(c.to_string(),"(Changed AND Greater than 100) OR Less than 5");
+        assert!(test(&c,1,200)&&test(&c,3,3)&&!test(&c,200,200)&&!test(&c,1,50));
+        let c=parse("changed AND (> 100 OR < 5)");
+        assert!(!test(&c,3,3)&&test(&c,4,3));
+        let c=parse("NOT unchanged OR increased by 5");
+        assert!(test(&c,1,2)&&!test(&c,2,2)&&test(&c,2,7));
+        assert!(test(&parse("!<3"),0,3)&&!test(&parse("!<3"),0,2));
+        assert!(test(&parse("not not changed"),1,2));
+        assert!(parse("increased by 5").to_string()=="Increased by 5"&&test(&parse("increased by 5"),1,6));
+    }
+    #[test]
+    fn comparisons(){
+        let ge=parse(">= 10");
+        assert!(matches!(&ge,Cond::Or(v) if matches!(v.as_slice(),[Cond::Atom(a),Cond::Atom(b)] if a.mode==ScanMode::Greater&&b.mode==ScanMode::Exact)));
+        assert!(test(&ge,0,10)&&test(&ge,0,11)&&!test(&ge,0,9));
+        let le=parse("<=10");
+        assert!(test(&le,0,10)&&test(&le,0,-3)&&!test(&le,0,11));
+        assert!(test(&parse("= 7"),0,7)&&test(&parse("7"),0,7)&&test(&parse("!= 7"),0,8)&&!test(&parse("!=7"),0,7));
+        assert!(test(&parse("not equal 5"),0,6)&&!test(&parse("Not equal 5"),0,5)&&test(&parse("NOT not equal 5"),0,5));
+        for s in ["!= 5","not equal 5 AND changed","NOT (> 3 OR between 1..2)","increased by 2 OR decreased by 2","unchanged AND < 7"]{
+            let c=parse(s); assert_eq!(parse(&c.to_string()).to_string(),c.to_string(),"{s}");
+        }
+        let c=parse("changed AND >= 10");
+        assert!(c.needs_old()&&test(&c,3,10)&&!test(&c,10,10)&&!test(&c,3,9));
+    }
+    #[test]
+    fn tolerance(){
+        let c=Cond::parse("= float32:1.23",FloatMatch::Rounded(0)).unwrap();
+        let t=|x:f32|c.test_raw(&ValueType::Float32(0.0),0,&x.to_ne_bytes(),&x.to_ne_bytes(),None);
+        assert!(t(1.2301)&&t(1.2349)&&!t(1.236));
+        let tr=|input:&str,ty:ValueType,b:&[u8]|Cond::parse(input,FloatMatch::Truncated(0)).unwrap().test_raw(&ty,0,b,b,None);
+        for x in [0.7f32,2.3,4.35,-0.7]{assert!(tr(&format!("float32:{x}"),ValueType::Float32(0.0),&x.to_ne_bytes()),"{x}");}
+        assert!(tr("double:2.39",ValueType::Float32(0.0),&2.3999f32.to_ne_bytes()));
+        assert!(!tr("double:2.57",ValueType::Float64(0.0),&2.5699999f64.to_ne_bytes()));
+        assert!(tr("double:2.56",ValueType::Float64(0.0),&2.5699999f64.to_ne_bytes()));
+        assert!(tr("double:2.57",ValueType::Float64(0.0),&2.57f64.to_ne_bytes())&&tr("double:4.35",ValueType::Float64(0.0),&4.35f64.to_ne_bytes()));
+        let c=Cond::parse("float32:1.5",FloatMatch::Abs(0.1)).unwrap();
+        assert!(c.test_raw(&ValueType::Float32(0.0),0,&[0;4],&1.55f32.to_ne_bytes(),None));
+    }
+    #[test]
+    fn nan(){
+        let c=Cond::mode(ScanMode::Between,"float:0.5..1.5",FloatMatch::Epsilon).unwrap();
+        let t=|ty:ValueType,b:&[u8]|c.test_raw(&ty,0,b,b,None);
+        for ty in [ValueType::Float32(0.0),ValueType::Float64(0.0),ValueType::Float16(0.0),ValueType::big_endian(ValueType::Float32(0.0))]{
+            assert!(!t(ty.clone(),&vec![0xFF;ValueType::type_size(&ty)]),"{}",ValueType::type_to_string(&ty));
+        }
+        assert!(t(ValueType::Float32(0.0),&1.0f32.to_ne_bytes())&&t(ValueType::Float64(0.0),&1.5f64.to_ne_bytes()));
+        assert!(test(&parse("between -2..2"),0,-1));
+        assert!(!parse("> float:0.5").test_raw(&ValueType::Float64(0.0),0,&[0xFF;8],&[0xFF;8],None));
+    }
+    #[test]
+    fn errors(){
+        for s in ["","foo","changed AND","(changed","changed)","> ","between 5","increased by"]{
+            assert!(Cond::parse(s,FloatMatch::Epsilon).is_err(),"{s}");
+        }
+    }
+    #[test]
+    fn nesting(){
+        assert!(Cond::parse(&format!("{}changed{}","(".repeat(MAX_DEPTH-1),")".repeat(MAX_DEPTH-1)),FloatMatch::Epsilon).is_ok());
+        assert!(Cond::parse(&format!("{}changed{}","(".repeat(100_000),")".repeat(100_000)),FloatMatch::Epsilon).is_err());
+        assert!(Cond::parse(&format!("{}changed","NOT ".repeat(100_000)),FloatMatch::Epsilon).is_err());
+    }
+}
@@ -0,0 +1,232 @@
+use std::fmt;
+use crate::types::{Num, ValueType};
+
+/// Deepest evaluation stack an expression may need.
+const MAX_STACK:usize=32;
+/// Deepest nesting of parentheses, calls and unary operators the compiler recurses into.
+const MAX_DEPTH:usize=64;
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Var{New,Old,First,Addr}
+#[derive(Debug,Clone,Copy,PartialEq)]
+enum Bin{Add,Sub,Mul,Div,Rem,Shl,Shr,BitAnd,BitXor,BitOr,Eq,Ne,Lt,Le,Gt,Ge,And,Or,Min,Max}
+#[derive(Debug,Clone,Copy,PartialEq)]
+enum Un{Neg,Not,BitNot,Abs}
+#[derive(Debug,Clone,Copy)]
+enum Op{Push(Num),Load(Var),Un(Un),Bin(Bin)}
+
+/// A predicate over `new`, `old`, `first`, `input` and `addr` such as `new - old == 3 && new % 2 == 0`,
+/// compiled once into a stack program and run for every candidate. Integers stay exact, any float
+/// operand makes the operation float; comparisons and logic yield 0 or 1.
+#[derive(Debug,Clone)]
+pub struct Expr{src:String,ops:Vec<Op>,input:Option<ValueType>}
+impl Expr{
+    /// `input` is the value `input` stands for; referencing it without one is an error.
+    pub fn compile(src:&str,input:Option<ValueType>)->Result<Self,String>{
+        let toks=lex(src)?;
+        if toks.is_empty(){return Err("Expression is empty".into());}
+        let mut c=Compiler{toks:&toks,at:0,depth:0,ops:vec![],input:input.as_ref().and_then(ValueType::num)};
+        if input.is_some()&&c.input.is_none(){return Err("input must be a number".into());}
+        c.expr(0)?;
+        if let Some(t)=toks.get(c.at){return Err(format!("Unexpected {t}"));}
+        let mut depth=0usize;
+        for op in &c.ops{
+            match op{Op::Push(_)|Op::Load(_)=>depth+=1,Op::Bin(_)=>depth-=1,Op::Un(_)=>{}}
+            if depth>MAX_STACK{return Err("Expression too deep".into());}
+        }
+        Ok(Self{src:src.trim().to_string(),ops:c.ops,input})
+    }
+    pub fn uses(&self,v:Var)->bool{self.ops.iter().any(|o|matches!(o,Op::Load(x) if *x==v))}
+    pub fn input(&self)->Option<&ValueType>{self.input.as_ref()}
+    /// Runs the program; fails on values that are not numbers, a missing `first` and division by zero.
+    pub fn test(&self,new:&ValueType,old:&ValueType,first:Option<&ValueType>,addr:usize)->bool{
+        let load=|v:Var|match v{Var::New=>new.num(),Var::Old=>old.num(),Var::First=>first.and_then(ValueType::num),Var::Addr=>Some(Num::I(addr as i128))};
+        let mut st=[Num::I(0);MAX_STACK]; let mut n=0;
+        for op in &self.ops{
+            match *op{
+                Op::Push(x)=>{st[n]=x; n+=1;}
+                Op::Load(v)=>{let Some(x)=load(v) else{return false}; st[n]=x; n+=1;}
+                Op::Un(u)=>{let Some(x)=unary(u,st[n-1]) else{return false}; st[n-1]=x;}
+                Op::Bin(b)=>{let Some(x)=binary(b,st[n-2],st[n-1]) else{return false}; n-=1; st[n-1]=x;}
+            }
+        }
+        n==1&&truthy(st[0])
+    }
+}
+impl fmt::Display for Expr{fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{write!(f,"{}",self.src)}}
+
+#[inline] fn truthy(x:Num)->bool{match x{Num::I(i)=>i!=0,Num::F(f)=>f!=0.0}}
+#[inline] fn flag(b:bool)->Num{Num::I(b as i128)}
+fn unary(u:Un,x:Num)->Option<Num>{
+    Some(match(u,x){
+        (Un::Neg,Num::I(i))=>Num::I(i.checked_neg()?),(Un::Neg,Num::F(f))=>Num::F(-f),
+        (Un::Abs,Num::I(i))=>Num::I(i.checked_abs()?),(Un::Abs,Num::F(f))=>Num::F(f.abs()),
+        (Un::Not,x)=>flag(!truthy(x)),
+        (Un::BitNot,Num::I(i))=>Num::I(!i),(Un::BitNot,Num::F(_))=>return None,
+    })
+}
+fn binary(b:Bin,x:Num,y:Num)->Option<Num>{
+    use Bin::*;
+    Some(match b{
+        And=>flag(truthy(x)&&truthy(y)),Or=>flag(truthy(x)||truthy(y)),
+        _=>match(x,y){
+            (Num::I(a),Num::I(c))=>match b{
+                Add=>Num::I(a.checked_add(c)?),Sub=>Num::I(a.checked_sub(c)?),Mul=>Num::I(a.checked_mul(c)?),
+                Div=>Num::I(a.checked_div(c)?),Rem=>Num::I(a.checked_rem(c)?),
+                Shl=>Num::I(a.checked_shl(u32::try_from(c).ok()?)?),Shr=>Num::I(a.checked_shr(u32::try_from(c).ok()?)?),
+                BitAnd=>Num::I(a&c),BitXor=>Num::I(a^c),BitOr=>Num::I(a|c),
+                Eq=>flag(a==c),Ne=>flag(a!=c),Lt=>flag(a<c),Le=>flag(a<=c),Gt=>flag(a>c),Ge=>flag(a>=c),
+                Min=>Num::I(a.min(c)),Max=>Num::I(a.max(c)),
+                And|Or=>unreachable!(),
+            },
+            _=>{
+                let (a,c)=(x.f64(),y.f64());
+                match b{
+                    Add=>Num::F(a+c),Sub=>Num::F(a-c),Mul=>Num::F(a*c),
+                    Div=>if c==0.0{return None}else{Num::F(a/c)},Rem=>if c==0.0{return None}else{Num::F(a%c)},
+                    Eq=>flag(a==c),Ne=>flag(a!=c),Lt=>flag(a<c),Le=>flag(a<=c),Gt=>flag(a>c),Ge=>flag(a>=c),
+                    Min=>Num::F(a.min(c)),Max=>Num::F(a.max(c)),
+                    _=>return None,
+                }
+            }
+        },
+    })
+}
+
+#[derive(Debug,Clone,PartialEq)]
+enum Tok{Num(Num),Id(String),Op(&'static str)}
+impl fmt::Display for Tok{
+    fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
+        match self{Tok::Num(Num::I(i))=>write!(f,"{i}"),Tok::Num(Num::F(x))=>write!(f,"{x}"),Tok::Id(s)=>write!(f,"{s}"),Tok::Op(o)=>write!(f,"{o}")}
+    }
+}
+
+const SYMS:&[&str]=&["==","!=","<=",">=","<<",">>","&&","||","+","-","*","/","%","<",">","!","~","&","^","|","(",")",","];
+
+fn lex(s:&str)->Result<Vec<Tok>,String>{
+    let (b,mut i,mut v)=(s.as_bytes(),0,vec![]);
+    while i<b.len(){
+        let c=b[i];
+        if c.is_ascii_whitespace(){i+=1; continue;}
+        if c.is_ascii_digit()||(c==b'.'&&b.get(i+1).is_some_and(u8::is_ascii_digit)){
+            let st=i;
+            while i<b.len()&&(b[i].is_ascii_alphanumeric()||b[i]==b'.'){i+=1;}
+            let t=&s[st..i];
+            let n=if let Some(h)=t.strip_prefix("0x"){i128::from_str_radix(h,16).ok().map(Num::I)}
+                else{t.parse::<i128>().ok().map(Num::I).or_else(||t.parse::<f64>().ok().map(Num::F))};
+            v.push(Tok::Num(n.ok_or_else(||format!("Bad number {t}"))?));
+        }else if c.is_ascii_alphabetic()||c==b'_'{
+            let st=i;
+            while i<b.len()&&(b[i].is_ascii_alphanumeric()||b[i]==b'_'){i+=1;}
+            v.push(Tok::Id(s[st..i].to_ascii_lowercase()));
+        }else{
+            let op=SYMS.iter().find(|o|s[i..].starts_with(**o)).ok_or_else(||format!("Unexpected {:?}",&s[i..].chars().next().unwrap_or(' ')))?;
+            v.push(Tok::Op(op)); i+=op.len();
+        }
+    }
+    Ok(v)
+}
+
+/// Precedence climbing straight into stack code, lowest binding first.
+struct Compiler<'a>{toks:&'a [Tok],at:usize,depth:usize,ops:Vec<Op>,input:Option<Num>}
+impl Compiler<'_>{
+    fn bin(op:&str)->Option<(u8,Bin)>{
+        use Bin::*;
+        Some(match op{
+            "||"=>(1,Or),"&&"=>(2,And),
+            "=="=>(3,Eq),"!="=>(3,Ne),"<"=>(3,Lt),"<="=>(3,Le),">"=>(3,Gt),">="=>(3,Ge),
+            "|"=>(4,BitOr),"^"=>(5,BitXor),"&"=>(6,BitAnd),"<<"=>(7,Shl),">>"=>(7,Shr),
+            "+"=>(8,Add),"-"=>(8,Sub),"*"=>(9,Mul),"/"=>(9,Div),"%"=>(9,Rem),
+            _=>return None,
+        })
+    }
+    fn eat(&mut self,op:&str)->bool{let hit=self.toks.get(self.at)==Some(&Tok::Op(SYMS.iter().find(|s|**s==op).unwrap())); if hit{self.at+=1;} hit}
+    fn expect(&mut self,op:&str)->Result<(),String>{if self.eat(op){Ok(())}else{Err(format!("Expected {op}"))}}
+    fn expr(&mut self,min:u8)->Result<(),String>{
+        self.unary()?;
+        while let Some(Tok::Op(o))=self.toks.get(self.at){
+            let Some((p,b))=Self::bin(o) else{break};
+            if p<=min{break;}
+            self.at+=1; self.expr(p)?; self.ops.push(Op::Bin(b));
+        }
+        Ok(())
+    }
+    /// Every level of nesting passes through here, so this is where the depth is bounded.
+    fn unary(&mut self)->Result<(),String>{
+        if self.depth>=MAX_DEPTH{return Err("Expression nested too deep".into());}
+        self.depth+=1; let r=self.prefix(); self.depth-=1; r
+    }
+    fn prefix(&mut self)->Result<(),String>{
+        for (s,u) in [("-",Un::Neg),("!",Un::Not),("~",Un::BitNot)]{
+            if self.eat(s){self.unary()?; self.ops.push(Op::Un(u)); return Ok(());}
+        }
+        self.primary()
+    }
+    fn primary(&mut self)->Result<(),String>{
+        let t=self.toks.get(self.at).cloned().ok_or("Unexpected end")?;
+        self.at+=1;
+        match t{
+            Tok::Num(n)=>self.ops.push(Op::Push(n)),
+            Tok::Op("(")=>{self.expr(0)?; self.expect(")")?;}
+            Tok::Id(id)=>match id.as_str(){
+                "new"=>self.ops.push(Op::Load(Var::New)),"old"=>self.ops.push(Op::Load(Var::Old)),
+                "first"=>self.ops.push(Op::Load(Var::First)),"addr"=>self.ops.push(Op::Load(Var::Addr)),
+                "input"=>self.ops.push(Op::Push(self.input.ok_or("input is not set")?)),
+                "abs"=>{self.expect("(")?; self.expr(0)?; self.expect(")")?; self.ops.push(Op::Un(Un::Abs));}
+                "min"|"max"=>{
+                    self.expect("(")?; self.expr(0)?; self.expect(",")?; self.expr(0)?; self.expect(")")?;
+                    self.ops.push(Op::Bin(if id=="min"{Bin::Min}else{Bin::Max}));
+                }
+                _=>return Err(format!("Unknown name {id}")),
+            },
+            t=>return Err(format!("Unexpected {t}")),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests{
+    use super::*;
+    fn run(src:&str,new:i32,old:i32)->bool{Expr::compile(src,None).unwrap().test(&ValueType::Int32(new),&ValueType::Int32(old),None,0x1000)}
+
+    #[test]
+    fn precedence(){
+        assert!(run("new == 1 + 2 * 3",7,0));
+        assert!(run("(1 + 2) * 3 == new",9,0));
+        assert!(run("new - old == 3 && new % 2 == 0",8,5));
+        assert!(!run("new - old == 3 && new % 2 == 0",7,4));
+        assert!(run("1 | 2 ^ 3 & 4 == 3",0,0));
+        assert!(run("1 << 2 + 1 == 8",0,0));
+        assert!(run("-new * 2 == -10 || 0",5,0));
+        assert!(run("!0 && ~0 == -1",0,0));
+    }
+    #[test]
+    fn values(){
+        assert!(run("abs(old - new) == 4 && min(new, old) == 1 && max(new, old) == 5",1,5));
+        assert!(run("addr == 0x1000",0,0));
+        assert!(run("new / 2.0 == 1.5",3,0));
+        assert!(!run("new / old",1,0));
+        let e=Expr::compile("new > first * 2",None).unwrap();
+        assert!(e.uses(Var::First)&&!e.uses(Var::Old));
+        assert!(e.test(&ValueType::Int32(7),&ValueType::Int32(0),Some(&ValueType::Int32(3)),0));
+        assert!(!e.test(&ValueType::Int32(7),&ValueType::Int32(0),None,0));
+        assert!(Expr::compile("new == input",Some(ValueType::Int32(4))).unwrap().test(&ValueType::Int32(4),&ValueType::Int32(0),None,0));
+    }
+    #[test]
+    fn errors(){
+        for src in ["","new +","(new","new == 1)","foo > 1","abs new","min(new)","new $ 1","input > 1","0x"]{
+            assert!(Expr::compile(src,None).is_err(),"{src}");
+        }
+        assert_eq!(Expr::compile("foo",None).unwrap_err(),"Unknown name foo");
+    }
+    #[test]
+    fn nesting(){
+        let deep=|n|format!("{}new{}","(".repeat(n),")".repeat(n));
+        assert!(Expr::compile(&deep(MAX_DEPTH-1),None).is_ok());
+        assert!(Expr::compile(&deep(100_000),None).is_err());
+        assert!(Expr::compile(&format!("{}new","-".repeat(100_000)),None).is_err());
+        assert!(Expr::compile(&format!("{}new","!".repeat(100_000)),None).is_err());
+        assert!(Expr::compile(&(0..40).map(|_|"new").collect::<Vec<_>>().join("+"),None).is_ok());
+    }
+}
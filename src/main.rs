@@ -1,4 +1,4 @@
-mod scan; mod types; mod ui;
+mod cond; mod expr; mod mem; mod pointer; mod scan; mod store; mod types; mod ui;
 fn main(){
     let pid=std::env::args().nth(1).and_then(|s|s.parse::<i32>().ok()).unwrap_or(0);
     let opt=eframe::NativeOptions::default();
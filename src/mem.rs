@@ -0,0 +1,103 @@
+use std::{
+    fs::File,
+    io::{self, IoSlice, IoSliceMut},
+    os::unix::fs::FileExt,
+    sync::{atomic::{AtomicU8, Ordering}, Arc},
+};
+use nix::{sys::uio::{process_vm_readv, process_vm_writev, RemoteIoVec}, unistd::Pid};
+
+const IOV_MAX:usize=1024;
+
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum Backend{Auto,ProcMem,VmRw}
+pub const BACKENDS:&[Backend]=&[Backend::Auto,Backend::ProcMem,Backend::VmRw];
+impl Backend{
+    pub fn name(&self)->&'static str{match self{Self::Auto=>"Auto",Self::ProcMem=>"/proc/pid/mem",Self::VmRw=>"process_vm_readv"}}
+    fn from_u8(v:u8)->Self{if v==1{Self::ProcMem}else{Self::VmRw}}
+    fn other(&self)->Self{if *self==Self::ProcMem{Self::VmRw}else{Self::ProcMem}}
+}
+
+/// Cheap to clone handle to a target's memory. Reads go through the active backend; with `Auto` they
+/// switch to the other one for good when the kernel denies it (ptrace_scope, seccomp, missing /proc
+/// access), while an explicitly chosen backend is the only one used and reports its errors.
+#[derive(Clone)]
+pub struct Mem{pid:Pid,file:Option<Arc<File>>,active:Arc<AtomicU8>,auto:bool}
+impl Mem{
+    pub fn open(pid:i32,backend:Backend)->io::Result<Self>{
+        if std::fs::metadata(format!("/proc/{pid}")).is_err(){return Err(io::Error::new(io::ErrorKind::NotFound,format!("no process {pid}")));}
+        let path=format!("/proc/{pid}/mem");
+        let file=File::options().read(true).write(true).open(&path).or_else(|_|File::open(&path));
+        let file=if backend==Backend::ProcMem{Some(Arc::new(file?))}else{file.ok().map(Arc::new)};
+        let first=if backend==Backend::ProcMem{Backend::ProcMem}else{Backend::VmRw};
+        Ok(Self{pid:Pid::from_raw(pid),file,active:Arc::new(AtomicU8::new(first as u8)),auto:backend==Backend::Auto})
+    }
+    pub fn backend(&self)->Backend{Backend::from_u8(self.active.load(Ordering::Relaxed))}
+    #[inline] fn denied(e:&io::Error)->bool{matches!(e.kind(),io::ErrorKind::PermissionDenied|io::ErrorKind::Unsupported)}
+    fn file(&self)->io::Result<&File>{self.file.as_deref().ok_or_else(||io::Error::from(io::ErrorKind::PermissionDenied))}
+
+    fn read_with(&self,b:Backend,addr:usize,buf:&mut [u8])->io::Result<()>{
+        match b{
+            Backend::VmRw=>{
+                let len=buf.len();
+                let n=process_vm_readv(self.pid,&mut [IoSliceMut::new(buf)],&[RemoteIoVec{base:addr,len}])?;
+                if n==len{Ok(())}else{Err(io::Error::from_raw_os_error(libc::EFAULT))}
+            }
+            _=>self.file()?.read_exact_at(buf,addr as u64),
+        }
+    }
+    fn write_with(&self,b:Backend,addr:usize,data:&[u8])->io::Result<()>{
+        match b{
+            Backend::VmRw=>{
+                let n=process_vm_writev(self.pid,&[IoSlice::new(data)],&[RemoteIoVec{base:addr,len:data.len()}])?;
+                if n==data.len(){Ok(())}else{Err(io::Error::from_raw_os_error(libc::EFAULT))}
+            }
+            _=>self.file()?.write_all_at(data,addr as u64),
+        }
+    }
+    fn fall_back(&self,from:Backend){let _=self.active.compare_exchange(from as u8,from.other() as u8,Ordering::Relaxed,Ordering::Relaxed);}
+
+    pub fn read_at(&self,addr:usize,buf:&mut [u8])->io::Result<()>{
+        let b=self.backend();
+        match self.read_with(b,addr,buf){
+            Err(e) if self.auto&&Self::denied(&e)=>{let r=self.read_with(b.other(),addr,buf); if r.is_ok(){self.fall_back(b);} r}
+            r=>r,
+        }
+    }
+    pub fn read(&self,addr:usize,len:usize)->io::Result<Vec<u8>>{let mut b=vec![0;len]; self.read_at(addr,&mut b)?; Ok(b)}
+    /// With `Auto`, writes also retry through /proc/pid/mem on any vm error, since process_vm_writev
+    /// cannot write to read-only mappings.
+    pub fn write_at(&self,addr:usize,data:&[u8])->io::Result<()>{
+        let b=self.backend();
+        match self.write_with(b,addr,data){
+            Err(e) if self.auto&&(Self::denied(&e)||(b==Backend::VmRw&&self.file.is_some()))=>{
+                let r=self.write_with(b.other(),addr,data); if r.is_ok()&&Self::denied(&e){self.fall_back(b);} r
+            }
+            r=>r,
+        }
+    }
+
+    /// Scatter-gather read of every `(addr,buf)` pair, one process_vm_readv per `IOV_MAX` entries.
+    /// Returns which entries were filled; a failing entry is skipped and the batch resumes after it.
+    pub fn read_many(&self,reqs:&mut [(usize,&mut [u8])])->Vec<bool>{
+        let mut ok=vec![false;reqs.len()];
+        if self.backend()==Backend::VmRw{
+            let mut i=0;
+            while i<reqs.len(){
+                let end=(i+IOV_MAX).min(reqs.len());
+                let remote:Vec<RemoteIoVec>=reqs[i..end].iter().map(|(a,b)|RemoteIoVec{base:*a,len:b.len()}).collect();
+                let mut local:Vec<IoSliceMut>=reqs[i..end].iter_mut().map(|(_,b)|IoSliceMut::new(b)).collect();
+                match process_vm_readv(self.pid,&mut local,&remote){
+                    Ok(mut n)=>{
+                        while i<end&&n>=reqs[i].1.len(){n-=reqs[i].1.len(); ok[i]=true; i+=1;}
+                        if i<end{i+=1;}
+                    }
+                    Err(e) if Self::denied(&io::Error::from(e))=>{if self.auto{self.fall_back(Backend::VmRw);} break;}
+                    Err(_)=>i+=1,
+                }
+            }
+            if self.backend()==Backend::VmRw{return ok;}
+        }
+        for (k,(a,b)) in reqs.iter_mut().enumerate(){if !ok[k]{ok[k]=self.read_at(*a,b).is_ok();}}
+        ok
+    }
+}
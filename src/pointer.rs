@@ -0,0 +1,182 @@
+use std::{
+    collections::HashSet,
+    fmt,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+use crate::mem::Mem;
+use crate::scan::MemoryScanner;
+use crate::types::{MemoryRegion, RegionGroup};
+
+const PTR:usize=std::mem::size_of::<usize>();
+/// A scan stops once this many chains are found.
+const MAX_CHAINS:usize=1<<16;
+/// Addresses expanded per level, so one wide level cannot exhaust memory.
+const MAX_FRONTIER:usize=1<<20;
+const MAGIC:&[u8;8]=b"SMEMPTR1";
+
+/// File-backed mapping a chain can start from, based at its lowest mapped address.
+#[derive(Clone)]
+pub struct Module{pub name:String,pub base:usize,pub regions:Vec<MemoryRegion>}
+impl Module{
+    pub fn contains(&self,a:usize)->bool{self.regions.iter().any(|r|(r.start..r.end).contains(&a))}
+    #[inline] pub fn short(name:&str)->&str{name.rsplit('/').next().unwrap_or(name)}
+}
+/// Modules among `groups`, i.e. every group named after a file.
+pub fn modules(groups:&[RegionGroup])->Vec<Module>{
+    groups.iter().filter(|g|g.name.starts_with('/')&&!g.regions.is_empty())
+        .map(|g|Module{name:g.name.clone(),base:g.regions.iter().map(|r|r.start).min().unwrap_or(0),regions:g.regions.clone()}).collect()
+}
+
+/// `module+base_off -> +o1 -> ... -> +on`: the pointer stored at `module+base_off` plus `o1` is the next
+/// address, and so on; the last sum is the target.
+#[derive(Clone,PartialEq,Eq,Hash)]
+pub struct PointerChain{pub module:String,pub base_off:usize,pub offsets:Vec<usize>}
+impl PointerChain{
+    /// Follows the chain through live memory; `None` if the module is gone or a link is unreadable.
+    pub fn resolve(&self,mem:&Mem,mods:&[Module])->Option<usize>{
+        let mut a=mods.iter().find(|m|m.name==self.module)?.base+self.base_off;
+        for &o in &self.offsets{a=read_ptr(mem,a)?.checked_add(o)?;}
+        Some(a)
+    }
+}
+impl fmt::Display for PointerChain{
+    fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
+        write!(f,"{}+0x{:x}",Module::short(&self.module),self.base_off)?;
+        for o in &self.offsets{write!(f," → 0x{o:x}")?;}
+        Ok(())
+    }
+}
+
+#[derive(Clone)]
+pub struct PointerParams{pub target:usize,pub depth:usize,pub max_off:usize}
+
+#[inline] fn read_ptr(mem:&Mem,a:usize)->Option<usize>{let mut b=[0u8;PTR]; mem.read_at(a,&mut b).ok().map(|_|usize::from_ne_bytes(b))}
+
+/// Every aligned word in `regions` whose value points into one of them, as `(value,addr)` sorted by value.
+fn collect_pointers(mem:&Mem,regions:&[MemoryRegion])->Vec<(usize,usize)>{
+    let valid=|v:usize|{let i=regions.partition_point(|r|r.end<=v); i<regions.len()&&regions[i].start<=v};
+    let parts=MemoryScanner::par_map(regions.len(),|i|{
+        let mut v=vec![];
+        MemoryScanner::for_each_chunk(mem,&regions[i],0,|base,buf,own|{
+            let mut k=(PTR-base%PTR)%PTR;
+            while k+PTR<=own{
+                let p=usize::from_ne_bytes(buf[k..k+PTR].try_into().unwrap());
+                if valid(p){v.push((p,base+k));}
+                k+=PTR;
+            }
+        });
+        v
+    });
+    let mut v:Vec<(usize,usize)>=parts.into_iter().flatten().collect();
+    v.sort_unstable(); v
+}
+
+/// Pointers of one run of the target: every `(value,addr)` pair pointing into a mapped region, the module
+/// layout and the address the chains must reach. Saved to a file, it lets chains found in one run be
+/// checked against later runs without the process.
+pub struct PointerMap{pub target:usize,pub modules:Vec<Module>,pub ptrs:Vec<(usize,usize)>,by_addr:Vec<(usize,usize)>}
+impl PointerMap{
+    fn new(target:usize,modules:Vec<Module>,mut ptrs:Vec<(usize,usize)>)->Self{
+        ptrs.sort_unstable();
+        let mut by_addr:Vec<(usize,usize)>=ptrs.iter().map(|&(v,a)|(a,v)).collect(); by_addr.sort_unstable();
+        Self{target,modules,ptrs,by_addr}
+    }
+    /// Reads every pointer of the regions in `groups` from the live process.
+    pub fn capture(mem:&Mem,groups:&[RegionGroup],target:usize)->Result<Self,String>{
+        let mut regions:Vec<MemoryRegion>=groups.iter().flat_map(|g|g.regions.clone()).collect();
+        if regions.is_empty(){return Err("No regions loaded".into());}
+        regions.sort_by_key(|r|r.start);
+        Ok(Self::new(target,modules(groups),collect_pointers(mem,&regions)))
+    }
+    /// Follows `c` through the recorded pointers instead of live memory.
+    pub fn resolve(&self,c:&PointerChain)->Option<usize>{
+        let mut a=self.modules.iter().find(|m|m.name==c.module)?.base+c.base_off;
+        for &o in &c.offsets{
+            let i=self.by_addr.binary_search_by_key(&a,|e|e.0).ok()?;
+            a=self.by_addr[i].1.checked_add(o)?;
+        }
+        Some(a)
+    }
+
+    /// Searches backwards from the target: every pointer at most `max_off` below a wanted address is a
+    /// link, a link inside a module ends a chain, any other link becomes a wanted address of the next level.
+    pub fn scan(&self,depth:usize,max_off:usize)->Vec<PointerChain>{
+        let map=&self.ptrs;
+        // (address, parent index in the previous level, offset from the pointer stored here to the parent)
+        let mut levels:Vec<Vec<(usize,usize,usize)>>=vec![vec![(self.target,0,0)]];
+        let mut seen:HashSet<usize>=HashSet::from([self.target]);
+        let mut out=vec![];
+        'lv: for d in 0..depth{
+            let mut next=vec![];
+            for (pi,&(t,..)) in levels[d].iter().enumerate(){
+                let lo=map.partition_point(|e|e.0<t.saturating_sub(max_off));
+                for &(v,a) in map[lo..].iter().take_while(|e|e.0<=t){
+                    if let Some(m)=self.modules.iter().find(|m|m.contains(a)){
+                        let mut offsets=vec![t-v]; let (mut l,mut i)=(d,pi);
+                        while l>0{let n=levels[l][i]; offsets.push(n.2); i=n.1; l-=1;}
+                        out.push(PointerChain{module:m.name.clone(),base_off:a-m.base,offsets});
+                        if out.len()>=MAX_CHAINS{break 'lv;}
+                    }else if d+1<depth&&next.len()<MAX_FRONTIER&&seen.insert(a){next.push((a,pi,t-v));}
+                }
+            }
+            if next.is_empty(){break;}
+            levels.push(next);
+        }
+        out.sort_by(|a,b|a.offsets.len().cmp(&b.offsets.len()).then_with(||(&a.module,a.base_off).cmp(&(&b.module,b.base_off))));
+        out
+    }
+
+    /// Little-endian: magic, target, modules as (name length, name, region count, start/end pairs),
+    /// then the pointer count and `(value,addr)` pairs.
+    pub fn save(&self,path:impl AsRef<Path>)->io::Result<()>{
+        let mut w=BufWriter::new(File::create(path)?);
+        let n=|w:&mut BufWriter<File>,v:usize|w.write_all(&(v as u64).to_le_bytes());
+        w.write_all(MAGIC)?; n(&mut w,self.target)?; n(&mut w,self.modules.len())?;
+        for m in &self.modules{
+            n(&mut w,m.name.len())?; w.write_all(m.name.as_bytes())?; n(&mut w,m.regions.len())?;
+            for r in &m.regions{n(&mut w,r.start)?; n(&mut w,r.end)?;}
+        }
+        n(&mut w,self.ptrs.len())?;
+        for &(v,a) in &self.ptrs{n(&mut w,v)?; n(&mut w,a)?;}
+        w.flush()
+    }
+    pub fn load(path:impl AsRef<Path>)->io::Result<Self>{
+        let f=File::open(path)?; let size=f.metadata()?.len();
+        let mut r=BufReader::new(f);
+        let bad=||io::Error::new(io::ErrorKind::InvalidData,"not a pointer map");
+        let mut magic=[0u8;8]; r.read_exact(&mut magic)?; if &magic!=MAGIC{return Err(bad());}
+        let n=|r:&mut BufReader<File>|->io::Result<usize>{let mut b=[0u8;8]; r.read_exact(&mut b)?; usize::try_from(u64::from_le_bytes(b)).map_err(|_|bad())};
+        // counts and lengths beyond the file size mean a corrupt file, not a huge allocation
+        let count=|r:&mut BufReader<File>|->io::Result<usize>{let v=n(r)?; if v as u64>size{Err(bad())}else{Ok(v)}};
+        let target=n(&mut r)?;
+        let mut modules=vec![];
+        for _ in 0..count(&mut r)?{
+            let mut name=vec![0u8;count(&mut r)?]; r.read_exact(&mut name)?;
+            let regions=(0..count(&mut r)?).map(|_|Ok(MemoryRegion{start:n(&mut r)?,end:n(&mut r)?,..Default::default()})).collect::<io::Result<Vec<_>>>()?;
+            let base=regions.iter().map(|r|r.start).min().unwrap_or(0);
+            modules.push(Module{name:String::from_utf8(name).map_err(|_|bad())?,base,regions});
+        }
+        let ptrs=(0..count(&mut r)?).map(|_|Ok((n(&mut r)?,n(&mut r)?))).collect::<io::Result<Vec<_>>>()?;
+        Ok(Self::new(target,modules,ptrs))
+    }
+}
+
+/// Captures the live pointer map and scans it; the map is returned too so it can be saved.
+pub fn pointer_scan(mem:Mem,groups:&[RegionGroup],p:&PointerParams)->Result<(Vec<PointerChain>,PointerMap),String>{
+    if p.depth==0{return Err("Depth must be at least 1".into());}
+    let map=PointerMap::capture(&mem,groups,p.target)?;
+    Ok((map.scan(p.depth,p.max_off),map))
+}
+
+/// Keeps the chains that reach each map's own target within that map, i.e. in every recorded run.
+pub fn intersect(chains:&[PointerChain],maps:&[PointerMap])->Vec<PointerChain>{
+    chains.iter().filter(|c|maps.iter().all(|m|m.resolve(c)==Some(m.target))).cloned().collect()
+}
+
+/// Keeps the chains that still lead to `target` in the live process.
+pub fn recheck(mem:&Mem,groups:&[RegionGroup],chains:&[PointerChain],target:usize)->Vec<PointerChain>{
+    let mods=modules(groups);
+    chains.iter().filter(|c|c.resolve(mem,&mods)==Some(target)).cloned().collect()
+}
@@ -2,51 +2,81 @@ use std::{
     collections::HashMap,
     error::Error,
     fs::File,
-    io::{self, BufRead, BufReader, Read, Seek, SeekFrom, Write},
-    sync::{Arc, Mutex},
+    io::{self, BufRead, BufReader},
+    os::unix::fs::FileExt,
+    sync::{atomic::{AtomicUsize, Ordering}, Arc, Mutex},
     thread,
     time::Duration,
 };
-use crate::types::{MemoryRegion, RegionGroup, ValueType};
+use crate::mem::{Backend, Mem};
+use crate::store::{Column, Results, Scan, SnapRegion, Snapshot};
+use crate::cond::Cond;
+use crate::types::{MemoryRegion, Perms, RegionFilter, RegionGroup, ValueType};
+
+const CHUNK:usize=1<<20;
+const PAGE:usize=4096;
+const BATCH:usize=4096;
+/// Snapshot diffs keep working on raw snapshots until at most this many candidates remain.
+const MAX_HITS:usize=1<<22;
+
+/// Settings for one scan. `align` is the step between candidate addresses, `None` for each type's own
+/// size; `types` of `None` picks them from the condition's input.
+#[derive(Clone)]
+pub struct ScanParams{pub cond:Cond,pub types:Option<Vec<ValueType>>,pub align:Option<usize>}
 
 pub struct MemoryScanner{
-    mem_file:Option<File>,
+    mem:Option<Mem>,
+    pub backend:Backend,
     pub is_attached:bool,
     pub pid:i32,
-    pub scan_history:Vec<HashMap<usize,ValueType>>,
-    pub scan_types_history:Vec<HashMap<usize,(ValueType,String)>>,
-    pub scan_results:Vec<usize>,
 }
 impl MemoryScanner{
-    pub fn new(pid:i32)->Self{Self{mem_file:None,is_attached:false,pid,scan_history:vec![],scan_types_history:vec![],scan_results:vec![]}}
+    pub fn new(pid:i32)->Self{Self{mem:None,backend:Backend::Auto,is_attached:false,pid}}
     pub fn set_pid(&mut self,pid:i32){self.detach(); self.pid=pid;}
     pub fn attach(&mut self)->io::Result<()>{
         if self.is_attached||self.pid<=0{return Ok(());}
-        self.mem_file=Some(File::options().read(true).write(true).open(format!("/proc/{}/mem",self.pid))?);
+        self.mem=Some(Mem::open(self.pid,self.backend)?);
         self.is_attached=true; Ok(())
     }
-    pub fn detach(&mut self){self.mem_file=None; self.is_attached=false;}
-    fn file(&mut self)->io::Result<&mut File>{self.mem_file.as_mut().ok_or_else(||io::Error::new(io::ErrorKind::Other,"not attached"))}
-    pub fn read_memory(&mut self,addr:usize,len:usize)->io::Result<Vec<u8>>{
-        let f=self.file()?; f.seek(SeekFrom::Start(addr as u64))?;
-        let mut b=vec![0;len]; f.read_exact(&mut b)?; Ok(b)
-    }
-    pub fn write_memory(&mut self,addr:usize,data:&[u8])->io::Result<()>{
-        let f=self.file()?; f.seek(SeekFrom::Start(addr as u64))?; f.write_all(data)
+    pub fn detach(&mut self){self.mem=None; self.is_attached=false;}
+    pub fn set_backend(&mut self,b:Backend)->io::Result<()>{
+        self.backend=b;
+        if self.is_attached{self.detach(); self.attach()?;}
+        Ok(())
     }
+    /// Shared handle to the target's memory, usable from scan threads without holding the scanner lock.
+    pub fn mem(&self)->io::Result<Mem>{self.mem.clone().ok_or_else(||io::Error::new(io::ErrorKind::Other,"not attached"))}
+    fn handle(&self)->io::Result<&Mem>{self.mem.as_ref().ok_or_else(||io::Error::new(io::ErrorKind::Other,"not attached"))}
+    pub fn read_memory(&self,addr:usize,len:usize)->io::Result<Vec<u8>>{self.handle()?.read(addr,len)}
+    pub fn write_memory(&self,addr:usize,data:&[u8])->io::Result<()>{self.handle()?.write_at(addr,data)}
+    /// Readable mappings of the target grouped by path, with every column of `/proc/pid/maps`. Reads
+    /// `/proc/pid/smaps` instead when it can, whose per-mapping `Rss`, `Swap` and `Anonymous` lines fill
+    /// in the region's memory stats.
     pub fn load_maps(&self)->Result<Vec<RegionGroup>,Box<dyn Error>>{
         if self.pid<=0{return Ok(vec![]);}
-        let rdr=BufReader::new(File::open(format!("/proc/{}/maps",self.pid))?);
+        let f=File::open(format!("/proc/{}/smaps",self.pid)).or_else(|_|File::open(format!("/proc/{}/maps",self.pid)))?;
         let mut g:HashMap<String,Vec<MemoryRegion>>=HashMap::new();
-        for line in rdr.lines().flatten(){
+        let mut cur:Option<(String,MemoryRegion)>=None;
+        for line in BufReader::new(f).lines().map_while(Result::ok){
             let p:Vec<_>=line.split_whitespace().collect(); if p.len()<2{continue;}
+            if let Some(key)=p[0].strip_suffix(':'){
+                let Some((_,r))=&mut cur else{continue};
+                let kb=||p[1].parse::<usize>().unwrap_or(0)*1024;
+                match key{"Rss"=>r.rss=kb(),"Swap"=>r.swap=kb(),"Anonymous"=>r.anon=kb(),_=>{}}
+                continue;
+            }
+            if let Some((name,r))=cur.take(){g.entry(name).or_default().push(r);}
             let mut r=p[0].split('-'); let (s,e)=(r.next(),r.next()); if s.is_none()||e.is_none(){continue;}
-            if !p[1].contains('r'){continue;}
+            let perms=Perms::parse(p[1]); if !perms.read{continue;}
             let start=usize::from_str_radix(s.unwrap(),16)?; let end=usize::from_str_radix(e.unwrap(),16)?;
             if end<=start{continue;}
+            let offset=p.get(2).and_then(|o|u64::from_str_radix(o,16).ok()).unwrap_or(0);
+            let dev=p.get(3).and_then(|d|d.split_once(':')).and_then(|(a,b)|Some((u32::from_str_radix(a,16).ok()?,u32::from_str_radix(b,16).ok()?))).unwrap_or_default();
+            let inode=p.get(4).and_then(|i|i.parse().ok()).unwrap_or(0);
             let name=if p.len()>=6{p[5..].join(" ")}else{"[Anonymous]".to_string()};
-            g.entry(name).or_default().push(MemoryRegion{start,end});
+            cur=Some((name,MemoryRegion{start,end,perms,offset,dev,inode,..Default::default()}));
         }
+        if let Some((name,r))=cur{g.entry(name).or_default().push(r);}
         let mut v:Vec<RegionGroup>=g.into_iter().map(|(name,regions)|RegionGroup{name,enabled:true,regions}).collect();
         v.sort_by(|a,b|match(a.name.as_str(),b.name.as_str()){
             (x,y) if x==y=>std::cmp::Ordering::Equal,
@@ -58,110 +88,289 @@ impl MemoryScanner{
         });
         Ok(v)
     }
-    pub fn reset_scan(&mut self){self.scan_history.clear(); self.scan_types_history.clear(); self.scan_results.clear();}
-    pub fn previous_scan(&mut self){
-        if self.scan_history.len()>1{
-            self.scan_history.pop();
-            self.scan_types_history.pop();
-            let prev=self.scan_history.last().unwrap();
-            self.scan_results=prev.keys().copied().collect();
+
+    /// Path of the target's executable, the main module among the groups.
+    pub fn exe_path(&self)->Option<String>{std::fs::read_link(format!("/proc/{}/exe",self.pid)).ok().map(|p|p.to_string_lossy().into_owned())}
+
+    /// Enabled regions of the enabled groups (only `selected_region` if set) that pass `filter`, clipped
+    /// to `range` when given.
+    pub fn scan_regions(groups:&[RegionGroup],selected_region:&Option<String>,filter:&RegionFilter,range:Option<(usize,usize)>)->Vec<MemoryRegion>{
+        groups.iter().filter(|g|g.enabled&&selected_region.as_ref().map_or(true,|s|g.name==*s))
+            .flat_map(|g|g.regions.iter().filter(|r|r.enabled&&filter.allows(r)))
+            .filter_map(|r|{
+                let Some((lo,hi))=range else{return Some(r.clone())};
+                let (start,end)=(r.start.max(lo),r.end.min(hi));
+                (start<end).then(||MemoryRegion{start,end,..r.clone()})
+            }).collect()
+    }
+
+    /// Cuts `regions` down to the runs of pages that `/proc/pid/pagemap` reports present in RAM.
+    /// Never-touched pages would read back as freshly made zero pages and swapped-out ones would be
+    /// swapped in, so both are left out. Pages whose entries cannot be read are kept, leaving them to the
+    /// page-by-page fallback of the scan itself.
+    pub fn resident_runs(pid:i32,regions:&[MemoryRegion])->io::Result<Vec<MemoryRegion>>{
+        let f=File::open(format!("/proc/{pid}/pagemap"))?;
+        let (mut v,mut buf)=(vec![],vec![0u8;CHUNK]);
+        for r in regions{
+            let (mut pg,last)=(r.start/PAGE,(r.end+PAGE-1)/PAGE);
+            let mut run:Option<usize>=None;
+            while pg<last{
+                let n=(CHUNK/8).min(last-pg); let b=&mut buf[..n*8];
+                if f.read_exact_at(b,(pg*8) as u64).is_err(){b.fill(0xFF);}
+                for (k,e) in b.chunks_exact(8).enumerate(){
+                    let at=((pg+k)*PAGE).max(r.start);
+                    match(u64::from_le_bytes(e.try_into().unwrap())>>63!=0,run){
+                        (true,None)=>run=Some(at),
+                        (false,Some(s))=>{v.push(MemoryRegion{start:s,end:at,..r.clone()}); run=None;}
+                        _=>{}
+                    }
+                }
+                pg+=n;
+            }
+            if let Some(s)=run{v.push(MemoryRegion{start:s,end:r.end,..r.clone()});}
         }
+        Ok(v)
     }
 
-    pub fn first_scan(
-        &mut self,
-        groups:&[RegionGroup],
-        selected_region:&Option<String>,
-        scan_value:&str,
-        scan_mode:&str,
-        type_filter:Option<&[ValueType]>,
-        scan_hist:&mut Vec<HashMap<usize,ValueType>>,
-        scan_types_hist:&mut Vec<HashMap<usize,(ValueType,String)>>,
-        scan_results:&mut Vec<usize>
-    )->Result<(),String>{
-        let val=ValueType::parse_user_value(scan_value).ok_or_else(||"Failed to parse value".to_string())?;
-        let scan_types=match type_filter{Some(t)=>t.to_vec(),None=>ValueType::scan_types(&val)};
-        let regions:Vec<MemoryRegion>=if let Some(sel)=selected_region{
-            groups.iter().filter(|g|g.enabled&&g.name==*sel).flat_map(|g|g.regions.clone()).collect()
-        }else{
-            groups.iter().filter(|g|g.enabled).flat_map(|g|g.regions.clone()).collect()
-        };
-        if regions.is_empty(){return Err("No enabled regions".into());}
-        let mut baseline:HashMap<usize,(ValueType,String)>=HashMap::new();
-        for region in regions{
-            let size=region.end.saturating_sub(region.start); if size==0{continue;}
-            if let Ok(buf)=self.read_memory(region.start,size){
-                for t in &scan_types{
-                    let ts=ValueType::type_size(t); if ts==0{continue;}
-                    let tname=ValueType::type_to_string(t).to_string();
-                    let mut i=0usize;
-                    while i+ts<=buf.len(){
-                        let a=region.start+i;
-                        let v=ValueType::from_bytes(buf[i..i+ts].to_vec(),t.clone());
-                        if scan_mode=="Exact"{ if v.equals(&val){baseline.insert(a,(v,tname.clone()));}}
-                        else{ baseline.insert(a,(v,tname.clone()));}
-                        i+=ts;
+    /// Walks `region` in `CHUNK`-sized windows that extend `overlap` bytes into the next one, calling
+    /// `f(base,buf,own)`; only offsets below `own` belong to this window, the rest is there so values
+    /// straddling the edge are still complete. A window that fails to read is retried page by page and
+    /// each readable run is reported on its own, so one bad page does not lose the whole region.
+    pub fn for_each_chunk(mem:&Mem,region:&MemoryRegion,overlap:usize,mut f:impl FnMut(usize,&[u8],usize)){
+        let mut buf=vec![0u8;CHUNK+overlap];
+        let mut at=region.start;
+        while at<region.end{
+            let own=CHUNK.min(region.end-at); let len=(own+overlap).min(region.end-at);
+            let b=&mut buf[..len];
+            if mem.read_at(at,b).is_ok(){f(at,b,own);}
+            else{
+                let (mut run,mut p)=(0usize,0usize);
+                while p<len{
+                    let n=(PAGE-(at+p)%PAGE).min(len-p);
+                    if mem.read_at(at+p,&mut b[p..p+n]).is_err(){
+                        if p>run&&run<own{f(at+run,&b[run..p],own.min(p)-run);}
+                        run=p+n;
                     }
+                    p+=n;
                 }
+                if len>run&&run<own{f(at+run,&b[run..len],own-run);}
             }
+            at+=own;
         }
-        scan_types_hist.push(baseline.clone());
-        scan_hist.push(baseline.iter().map(|(&k,(v,_))|(k,v.clone())).collect());
-        *scan_results=baseline.keys().copied().collect();
-        Ok(())
     }
 
-    pub fn next_scan(
-        &mut self,
-        scan_value:&str,
-        scan_mode:&str,
-        type_filter:Option<&[ValueType]>,
-        scan_hist:&mut Vec<HashMap<usize,ValueType>>,
-        scan_types_hist:&mut Vec<HashMap<usize,(ValueType,String)>>,
-        scan_results:&mut Vec<usize>
-    )->Result<(),String>{
-        if scan_hist.is_empty(){return Err("No baseline".into());}
-        let val=ValueType::parse_user_value(scan_value).ok_or("Failed to parse value")?;
-        let scan_types=match type_filter{Some(t)=>t.to_vec(),None=>ValueType::scan_types(&val)};
-        let prev=scan_types_hist.last().unwrap().clone();
-        let mut new_map:HashMap<usize,(ValueType,String)>=HashMap::new();
-        for (&addr,(old,_)) in &prev{
-            for t in &scan_types{
-                let ts=ValueType::type_size(t); if ts==0{continue;}
-                if let Ok(buf)=self.read_memory(addr,ts){
-                    if buf.len()==ts{
-                        let nv=ValueType::from_bytes(buf,t.clone());
-                        if ValueType::comparator(scan_mode,&old,&nv,&val){
-                            new_map.insert(addr,(nv,ValueType::type_to_string(t).to_string()));
-                        }
+    fn scan_region(f:&Mem,region:&MemoryRegion,p:&ScanParams,scan_types:&[ValueType])->Results{
+        let mut cols:Vec<Column>=scan_types.iter().map(|t|Column::new(t.clone(),p.align)).filter(|c|c.width>0).collect();
+        let overlap=cols.iter().map(|c|c.width).max().unwrap_or(1)-1;
+        Self::for_each_chunk(f,region,overlap,|base,buf,own|{
+            for c in cols.iter_mut(){
+                let (ts,step)=(c.width,c.align);
+                let mut i=(step-base%step)%step;
+                while i<own&&i+ts<=buf.len(){
+                    let raw=&buf[i..i+ts];
+                    if p.cond.test_raw(&c.ty,base+i,raw,raw,Some(raw)){c.push(base+i,raw);}
+                    i+=step;
+                }
+            }
+        });
+        Results{cols}
+    }
+
+    /// Runs `f` over `0..n` on a pool of worker threads and returns the results in index order. A panic
+    /// in a worker is passed on to the caller instead of quietly dropping that worker's share.
+    pub(crate) fn par_map<T:Send>(n:usize,f:impl Fn(usize)->T+Sync)->Vec<T>{
+        let workers=thread::available_parallelism().map_or(4,|n|n.get()).min(n.max(1));
+        let next=AtomicUsize::new(0);
+        let mut parts:Vec<(usize,T)>=thread::scope(|s|{
+            let hs:Vec<_>=(0..workers).map(|_|s.spawn(||{
+                let mut part=vec![];
+                loop{
+                    let i=next.fetch_add(1,Ordering::Relaxed); if i>=n{break;}
+                    part.push((i,f(i)));
+                }
+                part
+            })).collect();
+            hs.into_iter().flat_map(|h|h.join().unwrap_or_else(|e|std::panic::resume_unwind(e))).collect()
+        });
+        parts.sort_by_key(|p|p.0);
+        parts.into_iter().map(|p|p.1).collect()
+    }
+
+    /// Copies the readable parts of `region`, merging chunks that turn out contiguous.
+    fn snapshot_region(mem:&Mem,region:&MemoryRegion)->Vec<SnapRegion>{
+        let mut v:Vec<SnapRegion>=vec![];
+        Self::for_each_chunk(mem,region,0,|base,buf,own|match v.last_mut(){
+            Some(l) if l.start+l.data.len()==base=>l.data.extend_from_slice(&buf[..own]),
+            _=>v.push(SnapRegion{start:base,data:buf[..own].to_vec(),masks:None}),
+        });
+        v
+    }
+
+    /// Reads and matches `regions` on a pool of worker threads. Each region yields its own partial
+    /// result; those are stitched back together in address order, same as a serial pass. Modes that
+    /// compare against an earlier value have nothing to filter on yet, so they only snapshot the regions.
+    pub fn first_scan(mem:Mem,mut regions:Vec<MemoryRegion>,p:&ScanParams)->Result<Scan,String>{
+        let scan_types=p.types.clone().unwrap_or_else(||ValueType::scan_types(p.cond.hint().unwrap_or(&ValueType::Int8(0))));
+        if regions.is_empty(){return Err("No enabled regions".into());}
+        regions.sort_by_key(|r|r.start);
+        if p.cond.needs_old(){
+            let regions=Self::par_map(regions.len(),|i|Self::snapshot_region(&mem,&regions[i])).into_iter().flatten().collect();
+            return Ok(Scan::Snap(Snapshot::new(scan_types.into_iter().filter(|t|ValueType::type_size(t)>0).collect(),p.align,regions)));
+        }
+        let parts=Self::par_map(regions.len(),|i|Self::scan_region(&mem,&regions[i],p,&scan_types));
+        Ok(Scan::Hits(Results::concat(parts)))
+    }
+
+    /// Compares one snapshot run against live memory, returning the live copy with the surviving
+    /// candidates marked, or `None` when nothing in it survives. `old` and `first` are the run's data in the
+    /// reference and first scans, `types` pairs each type with its index in `r`'s masks, and slots off the
+    /// requested `p.align` are dropped.
+    fn diff_region(mem:&Mem,r:&SnapRegion,old:&[u8],first:Option<&[u8]>,types:&[(usize,ValueType)],align:Option<usize>,p:&ScanParams)->Option<(SnapRegion,usize)>{
+        let mut data=vec![0u8;r.data.len()];
+        let first_page=r.start/PAGE;
+        let mut readable=vec![false;(r.start+data.len()+PAGE-1)/PAGE-first_page];
+        let mut at=0;
+        while at<data.len(){
+            let n=CHUNK.min(data.len()-at);
+            if mem.read_at(r.start+at,&mut data[at..at+n]).is_ok(){
+                for pg in &mut readable[(r.start+at)/PAGE-first_page..(r.start+at+n+PAGE-1)/PAGE-first_page]{*pg=true;}
+            }else{
+                let mut p=at;
+                while p<at+n{
+                    let m=(PAGE-(r.start+p)%PAGE).min(at+n-p);
+                    if mem.read_at(r.start+p,&mut data[p..p+m]).is_ok(){readable[(r.start+p)/PAGE-first_page]=true;}
+                    p+=m;
+                }
+            }
+            at+=n;
+        }
+        let mut count=0;
+        let masks:Vec<Vec<u64>>=types.iter().map(|(t,ty)|{
+            let ts=ValueType::type_size(ty); let step=align.unwrap_or(ValueType::natural_align(ty)); let (off,n)=r.slots(ts,step);
+            let mut bits=vec![0u64;(n+63)/64];
+            for k in (0..n).filter(|&k|r.candidate(*t,k)){
+                let i=off+k*step; let a=r.start+i;
+                if a%p.align.unwrap_or(ValueType::natural_align(ty))!=0||!readable[a/PAGE-first_page]||!readable[(a+ts-1)/PAGE-first_page]{continue;}
+                if p.cond.test_raw(ty,a,&old[i..i+ts],&data[i..i+ts],first.map(|f|&f[i..i+ts])){bits[k/64]|=1<<(k%64); count+=1;}
+            }
+            bits
+        }).collect();
+        (count>0).then_some((SnapRegion{start:r.start,data,masks:Some(masks)},count))
+    }
+
+    /// Diffs a snapshot against live memory, and only lists addresses once few enough candidates remain.
+    /// Old values come from `reference`, an earlier snapshot whose runs start where `prev`'s do, and first
+    /// values from `first` likewise.
+    fn next_snapshot(mem:&Mem,prev:&Snapshot,reference:&Snapshot,first:Option<&Snapshot>,p:&ScanParams)->Scan{
+        let sel:Vec<(usize,ValueType)>=prev.types.iter().cloned().enumerate().filter(|(_,t)|p.types.as_ref().map_or(true,|f|f.contains(t))).collect();
+        fn run<'a>(s:&'a Snapshot,r:&'a SnapRegion)->&'a [u8]{s.regions.binary_search_by_key(&r.start,|x|x.start).ok().map(|j|&s.regions[j].data).filter(|d|d.len()==r.data.len()).unwrap_or(&r.data)}
+        let diffs=Self::par_map(prev.regions.len(),|i|{let r=&prev.regions[i]; Self::diff_region(mem,r,run(reference,r),first.map(|f|run(f,r)),&sel,prev.align,p)});
+        // a snapshot keeps its slot layout, so only same-width types are refined
+        let types=sel.into_iter().map(|t|{let n=Self::retype(&t.1,p); if ValueType::type_size(&n)==ValueType::type_size(&t.1){n}else{t.1}}).collect();
+        let count:usize=diffs.iter().flatten().map(|d|d.1).sum();
+        let snap=Snapshot{types,align:prev.align,regions:diffs.into_iter().flatten().map(|d|d.0).collect(),count};
+        if count<=MAX_HITS{Scan::Hits(snap.into_results())}else{Scan::Snap(snap)}
+    }
+
+    /// Splits sorted `addrs` into per-page spans `(start,end,first,last)` covering `width` bytes past
+    /// the last address, so a whole page of survivors is fetched with one read.
+    fn page_spans(addrs:&[usize],width:usize)->Vec<(usize,usize,usize,usize)>{
+        let mut v=vec![]; let mut i=0;
+        while i<addrs.len(){
+            let (s,page)=(addrs[i],addrs[i]/PAGE); let mut j=i+1;
+            while j<addrs.len()&&addrs[j]/PAGE==page{j+=1;}
+            v.push((s,addrs[j-1]+width,i,j)); i=j;
+        }
+        v
+    }
+
+    /// The type an exact scan refines a `ty` column to: text in the same encoding, a pattern, a group or an
+    /// unknown XOR key take the new input, anything else stays `ty`.
+    fn retype(ty:&ValueType,p:&ScanParams)->ValueType{
+        match(ty,p.cond.exact()){
+            (ValueType::Str{enc:a,..},Some(v@ValueType::Str{enc:b,..})) if a==b=>v.clone(),
+            (ValueType::Aob{..},Some(v@ValueType::Aob{..}))|(ValueType::Group{..},Some(v@ValueType::Group{..}))=>v.clone(),
+            (ValueType::Xor{key:None,..},Some(v@ValueType::Xor{key:None,..})) if ValueType::type_size(ty)==ValueType::type_size(v)=>v.clone(),
+            _=>ty.clone(),
+        }
+    }
+
+    /// Raw `width` bytes at `addr` of a value like `ty` in an earlier scan, if that scan recorded them.
+    fn old_bytes<'a>(reference:&'a Scan,ty:&ValueType,addr:usize,width:usize)->Option<&'a [u8]>{
+        match reference{
+            Scan::Hits(r)=>r.cols.iter().filter(|c|c.width==width&&std::mem::discriminant(&c.ty)==std::mem::discriminant(ty)).find_map(|c|c.find(addr).map(|i|c.raw(i))),
+            Scan::Snap(s)=>{
+                let i=s.regions.partition_point(|r|r.start<=addr).checked_sub(1)?;
+                let r=&s.regions[i]; r.data.get(addr-r.start..addr-r.start+width)
+            }
+        }
+    }
+
+    /// Re-reads the survivors of `prev` in page-sized spans, `BATCH` spans per scatter-gather call, and
+    /// decodes every address from the span buffers. Spans that fail to read fall back to per-address reads.
+    /// Each column is refined with its own type; a type filter drops the columns it does not name. An exact
+    /// text, byte pattern or group scan retypes matching columns to the new input, so a string or
+    /// signature can be followed as it grows or shrinks and group members refined with new values.
+    /// Old values come from `reference`, any earlier scan, instead of `prev` when given; survivors it did
+    /// not record are dropped by the modes that compare against them. `first` is the first scan, for
+    /// expressions that read `first`.
+    pub fn next_scan(mem:Mem,prev:&Scan,reference:Option<&Scan>,first:Option<&Scan>,p:&ScanParams)->Result<Scan,String>{
+        if p.cond.is_unknown(){return Err("Unknown Initial Value only starts a new scan".into());}
+        let first=first.filter(|_|p.cond.needs_first());
+        let prev=match (prev,reference,first){
+            (Scan::Hits(r),..)=>r,
+            (Scan::Snap(_),Some(Scan::Hits(_)),_)|(Scan::Snap(_),_,Some(Scan::Hits(_)))=>return Err("A snapshot can only be compared with an earlier snapshot".into()),
+            (Scan::Snap(s),rf,first)=>{
+                fn snap(x:Option<&Scan>)->Option<&Snapshot>{match x{Some(Scan::Snap(s))=>Some(s),_=>None}}
+                return Ok(Self::next_snapshot(&mem,s,snap(rf).unwrap_or(s),snap(first),p));
+            }
+        };
+        let mut out=Results::default();
+        for col in prev.cols.iter().filter(|c|p.types.as_ref().map_or(true,|t|t.contains(&c.ty))){
+            let ty=Self::retype(&col.ty,p);
+            let (ts,step)=(ValueType::type_size(&ty),p.align.unwrap_or(ValueType::natural_align(&ty)));
+            let mut nc=Column::new(ty,Some(col.align));
+            for spans in Self::page_spans(&col.addrs,ts).chunks(BATCH){
+                let mut bufs:Vec<Vec<u8>>=spans.iter().map(|&(s,e,..)|vec![0;e-s]).collect();
+                let ok=mem.read_many(&mut spans.iter().map(|sp|sp.0).zip(bufs.iter_mut().map(|b|b.as_mut_slice())).collect::<Vec<_>>());
+                for ((&(s,_,i,j),buf),ok) in spans.iter().zip(&bufs).zip(ok){
+                    for k in i..j{
+                        let addr=col.addrs[k]; if addr%step!=0{continue;}
+                        let bytes=if ok{buf[addr-s..addr-s+ts].to_vec()}else if let Ok(b)=mem.read(addr,ts){b}else{continue};
+                        let old=match reference{Some(rf) if p.cond.needs_old()=>match Self::old_bytes(rf,&col.ty,addr,col.width){Some(o)=>o,None=>continue},_=>col.raw(k)};
+                        let first=match first{Some(f)=>match Self::old_bytes(f,&col.ty,addr,col.width){Some(o)=>Some(o),None=>continue},None=>None};
+                        if p.cond.test_raw(&col.ty,addr,old,&bytes,first){nc.push(addr,&bytes);}
                     }
                 }
             }
+            if nc.len()>0{out.cols.push(nc);}
         }
-        scan_types_hist.push(new_map.clone());
-        scan_hist.push(new_map.iter().map(|(&k,(v,_))|(k,v.clone())).collect());
-        *scan_results=new_map.keys().copied().collect();
-        Ok(())
+        Ok(Scan::Hits(out))
     }
 
-    pub fn address_set(&mut self,scan_value:&str,scan_results:&mut Vec<usize>)->Result<(),String>{
-        let val=ValueType::parse_user_value(scan_value).ok_or("Bad value")?;
-        let bytes=val.to_bytes();
-        for a in scan_results.clone(){ self.write_memory(a,&bytes).map_err(|e|format!("0x{a:x} {e}"))?; }
+    /// Writes `bytes` at `addr`, keeping the bits `mask` leaves out as they are in memory.
+    fn write_masked(&self,addr:usize,bytes:&[u8],mask:Option<&[u8]>)->io::Result<()>{
+        let Some(mask)=mask else{return self.write_memory(addr,bytes)};
+        let mut cur=self.read_memory(addr,mask.len())?;
+        for ((c,b),m) in cur.iter_mut().zip(bytes).zip(mask){*c=(*c&!m)|(b&m);}
+        self.write_memory(addr,&cur)
+    }
+
+    /// Writes `scan_value` to every address; `nul` also terminates text with a zero unit, and pattern
+    /// wildcards leave their bytes untouched.
+    pub fn address_set(&mut self,scan_value:&str,scan_results:&[usize],nul:bool)->Result<(),String>{
+        let val=ValueType::parse_write_value(scan_value)?;
+        let (bytes,mask)=(val.write_bytes(nul),val.write_mask());
+        for &a in scan_results{ self.write_masked(a,&bytes,mask.as_deref()).map_err(|e|format!("0x{a:x} {e}"))?; }
         Ok(())
     }
 
-    pub fn address_set_lock(self_arc:Arc<Mutex<Self>>,scan_value:String){
+    pub fn address_set_lock(self_arc:Arc<Mutex<Self>>,val:ValueType,nul:bool,addrs:Vec<usize>){
         thread::spawn(move||{
+            let (bytes,mask)=(val.write_bytes(nul),val.write_mask());
             loop{
-                let (bytes,addrs)={
-                    let s=self_arc.lock().unwrap();
-                    (ValueType::parse_user_value(&scan_value).map(|v|v.to_bytes()),s.scan_results.clone())
-                };
-                if let Some(b)=bytes{
-                    for a in addrs{
-                        if let Ok(mut s)=self_arc.lock(){ let _=s.write_memory(a,&b); }
-                    }
+                for &a in &addrs{
+                    if let Ok(s)=self_arc.lock(){ let _=s.write_masked(a,&bytes,mask.as_deref()); }
                 }
                 thread::sleep(Duration::from_millis(100));
             }
@@ -0,0 +1,96 @@
+use crate::types::ValueType;
+
+/// Hits of a single type found at `align`-byte steps: ascending addresses and their raw values packed
+/// `width` bytes apart.
+#[derive(Clone)]
+pub struct Column{pub ty:ValueType,pub width:usize,pub align:usize,pub addrs:Vec<usize>,pub bytes:Vec<u8>}
+impl Column{
+    /// `align` of `None` is the type's natural alignment.
+    pub fn new(ty:ValueType,align:Option<usize>)->Self{let width=ValueType::type_size(&ty); Self{align:align.unwrap_or(ValueType::natural_align(&ty)),ty,width,addrs:vec![],bytes:vec![]}}
+    #[inline] pub fn len(&self)->usize{self.addrs.len()}
+    #[inline] pub fn push(&mut self,addr:usize,raw:&[u8]){self.addrs.push(addr); self.bytes.extend_from_slice(&raw[..self.width]);}
+    #[inline] pub fn raw(&self,i:usize)->&[u8]{&self.bytes[i*self.width..(i+1)*self.width]}
+    #[inline] pub fn find(&self,addr:usize)->Option<usize>{self.addrs.binary_search(&addr).ok()}
+    /// Appends `o`, which must start above this column's last address.
+    pub fn append(&mut self,mut o:Column){self.addrs.append(&mut o.addrs); self.bytes.append(&mut o.bytes);}
+}
+
+/// One scan's results, one column per scanned type.
+#[derive(Clone,Default)]
+pub struct Results{pub cols:Vec<Column>}
+impl Results{
+    pub fn len(&self)->usize{self.cols.iter().map(Column::len).sum()}
+    pub fn is_empty(&self)->bool{self.cols.iter().all(|c|c.addrs.is_empty())}
+    pub fn get(&self,addr:usize)->Option<(&Column,usize)>{self.cols.iter().find_map(|c|c.find(addr).map(|i|(c,i)))}
+    /// Maps a flat row number (columns laid end to end) to its column and index.
+    pub fn row(&self,mut r:usize)->Option<(&Column,usize)>{
+        for c in &self.cols{if r<c.len(){return Some((c,r));} r-=c.len();}
+        None
+    }
+    /// Every hit address once, ascending.
+    pub fn addresses(&self)->Vec<usize>{
+        let mut v:Vec<usize>=self.cols.iter().flat_map(|c|c.addrs.iter().copied()).collect();
+        v.sort_unstable(); v.dedup(); v
+    }
+    /// Concatenates per-region partial results given in ascending region order.
+    pub fn concat(parts:impl IntoIterator<Item=Results>)->Results{
+        let mut out=Results::default();
+        for p in parts{
+            for c in p.cols{
+                match out.cols.iter_mut().find(|o|o.ty==c.ty&&o.align==c.align){Some(o)=>o.append(c),None=>out.cols.push(c)}
+            }
+        }
+        out.cols.retain(|c|c.len()>0);
+        out
+    }
+}
+
+/// Raw copy of one readable run of target memory. `masks` holds one candidate bitset per snapshot
+/// type, indexed by slot; `None` means every slot is still a candidate.
+#[derive(Clone)]
+pub struct SnapRegion{pub start:usize,pub data:Vec<u8>,pub masks:Option<Vec<Vec<u64>>>}
+impl SnapRegion{
+    /// Offset of the first `step`-aligned slot and the number of whole `ts`-byte values starting on one.
+    pub fn slots(&self,ts:usize,step:usize)->(usize,usize){
+        let off=(step-self.start%step)%step;
+        (off,if self.data.len()>=off+ts{(self.data.len()-off-ts)/step+1}else{0})
+    }
+    #[inline] pub fn candidate(&self,t:usize,k:usize)->bool{self.masks.as_ref().map_or(true,|m|m[t][k/64]>>(k%64)&1==1)}
+    pub fn candidates(&self,types:&[ValueType],align:Option<usize>)->usize{
+        match &self.masks{
+            Some(m)=>m.iter().flatten().map(|w|w.count_ones() as usize).sum(),
+            None=>types.iter().map(|t|self.slots(ValueType::type_size(t),align.unwrap_or(ValueType::natural_align(t))).1).sum(),
+        }
+    }
+}
+
+/// Region snapshots taken instead of per-address records while the candidate set is too large to list.
+/// Slots are `align` bytes apart (natural alignment when `None`); `count` is the number of
+/// candidates, counted once when the snapshot is built.
+#[derive(Clone)]
+pub struct Snapshot{pub types:Vec<ValueType>,pub align:Option<usize>,pub regions:Vec<SnapRegion>,pub count:usize}
+impl Snapshot{
+    pub fn new(types:Vec<ValueType>,align:Option<usize>,regions:Vec<SnapRegion>)->Self{
+        let count=regions.iter().map(|r|r.candidates(&types,align)).sum();
+        Self{types,align,regions,count}
+    }
+    pub fn into_results(self)->Results{
+        let mut cols:Vec<Column>=self.types.iter().map(|t|Column::new(t.clone(),self.align)).collect();
+        for r in &self.regions{
+            for (t,c) in cols.iter_mut().enumerate(){
+                let (off,n)=r.slots(c.width,c.align);
+                for k in (0..n).filter(|&k|r.candidate(t,k)){let i=off+k*c.align; c.push(r.start+i,&r.data[i..i+c.width]);}
+            }
+        }
+        cols.retain(|c|c.len()>0);
+        Results{cols}
+    }
+}
+
+/// One entry of the scan history.
+#[derive(Clone)]
+pub enum Scan{Hits(Results),Snap(Snapshot)}
+impl Scan{
+    pub fn len(&self)->usize{match self{Self::Hits(r)=>r.len(),Self::Snap(s)=>s.count}}
+    pub fn hits(&self)->Option<&Results>{match self{Self::Hits(r)=>Some(r),Self::Snap(_)=>None}}
+}
@@ -1,16 +1,171 @@
+use std::cmp::Ordering;
+
+use std::fmt;
+
+/// Widest span a `group:` value may cover, members and window alike.
+const MAX_GROUP:usize=1<<16;
+
+/// One line of `/proc/pid/maps`: address range, permissions, file offset, device and inode (0 for
+/// anonymous memory), plus the `Rss`, `Swap` and `Anonymous` bytes from `/proc/pid/smaps` when it is
+/// readable. Scans skip regions that are not `enabled`.
 #[derive(Clone)]
-pub struct MemoryRegion{pub start:usize,pub end:usize}
+pub struct MemoryRegion{pub start:usize,pub end:usize,pub perms:Perms,pub offset:u64,pub dev:(u32,u32),pub inode:u64,pub rss:usize,pub swap:usize,pub anon:usize,pub enabled:bool}
+impl Default for MemoryRegion{
+    fn default()->Self{Self{start:0,end:0,perms:Perms::default(),offset:0,dev:(0,0),inode:0,rss:0,swap:0,anon:0,enabled:true}}
+}
+impl MemoryRegion{
+    #[inline] pub fn size(&self)->usize{self.end.saturating_sub(self.start)}
+    #[inline] pub fn is_file(&self)->bool{self.inode!=0}
+}
+#[derive(Clone,Copy,Default,PartialEq)]
+pub struct Perms{pub read:bool,pub write:bool,pub exec:bool,pub shared:bool}
+impl Perms{
+    /// Parses the `rw-p` column.
+    pub fn parse(s:&str)->Self{let b=s.as_bytes(); Self{read:b.first()==Some(&b'r'),write:b.get(1)==Some(&b'w'),exec:b.get(2)==Some(&b'x'),shared:b.get(3)==Some(&b's')}}
+}
+impl fmt::Display for Perms{
+    fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
+        let c=|on:bool,ch:char|if on{ch}else{'-'};
+        write!(f,"{}{}{}{}",c(self.read,'r'),c(self.write,'w'),c(self.exec,'x'),if self.shared{'s'}else{'p'})
+    }
+}
+
+/// Which regions a scan covers; each `None` leaves that property unfiltered.
+#[derive(Clone,Copy,Default,PartialEq)]
+pub struct RegionFilter{pub write:Option<bool>,pub exec:Option<bool>,pub shared:Option<bool>,pub file:Option<bool>}
+impl RegionFilter{
+    /// Private writable memory, where most values live.
+    pub const RW_PRIVATE:Self=Self{write:Some(true),exec:None,shared:Some(false),file:None};
+    pub const ANON_RW:Self=Self{write:Some(true),exec:None,shared:Some(false),file:Some(false)};
+    pub fn allows(&self,r:&MemoryRegion)->bool{
+        let ok=|want:Option<bool>,has:bool|want.map_or(true,|w|w==has);
+        ok(self.write,r.perms.write)&&ok(self.exec,r.perms.exec)&&ok(self.shared,r.perms.shared)&&ok(self.file,r.is_file())
+    }
+}
+impl fmt::Display for RegionFilter{
+    fn fmt(&self,f:&mut fmt::Formatter)->fmt::Result{
+        let parts:Vec<&str>=[(self.write,"writable","read-only"),(self.exec,"exec","no-exec"),(self.shared,"shared","private"),(self.file,"file","anonymous")]
+            .iter().filter_map(|&(o,y,n)|o.map(|o|if o{y}else{n})).collect();
+        if parts.is_empty(){write!(f,"all")}else{write!(f,"{}",parts.join(", "))}
+    }
+}
 
 #[derive(Clone)]
 pub struct RegionGroup{pub name:String,pub enabled:bool,pub regions:Vec<MemoryRegion>}
 
+/// A scalar widened for comparing across types.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub(crate) enum Num{I(i128),F(f64)}
+impl Num{pub(crate) fn f64(self)->f64{match self{Num::I(n)=>n as f64,Num::F(f)=>f}}}
+
+/// How float values are matched against typed input. `Rounded`/`Truncated` compare at the number of
+/// decimals typed in the input, `Abs`/`Rel` accept a difference up to an absolute or relative tolerance.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum FloatMatch{Epsilon,Rounded(u32),Truncated(u32),Abs(f64),Rel(f64)}
+impl FloatMatch{
+    /// Fills in the decimals typed in `input` for the rounding modes.
+    pub fn for_input(self,input:&str)->Self{
+        let t=input.rsplit(':').next().unwrap_or("").split("..").next().unwrap_or("").trim();
+        let d=t.split_once('.').map_or(0,|(_,f)|f.chars().take_while(|c|c.is_ascii_digit()).count() as u32);
+        match self{Self::Rounded(_)=>Self::Rounded(d),Self::Truncated(_)=>Self::Truncated(d),m=>m}
+    }
+    /// Whether a value `x` read from memory matches the typed `want`; `eps` is the relative precision
+    /// `x` was stored with.
+    pub fn eq(&self,x:f64,want:f64,eps:f64)->bool{
+        match *self{
+            Self::Epsilon=>(x-want).abs()<f64::EPSILON,
+            Self::Rounded(d)=>{let p=10f64.powi(d as i32); (x*p).round()==(want*p).round()}
+            // a stored 0.7f32 reads back as 0.6999999881, so nudge it outward by its precision before cutting
+            Self::Truncated(d)=>{let p=10f64.powi(d as i32); (x*p*(1.0+eps)).trunc()==(want*p).round()}
+            Self::Abs(t)=>(x-want).abs()<=t,
+            Self::Rel(t)=>(x-want).abs()<=t*want.abs(),
+        }
+    }
+}
+
+/// How a scan compares a value: against the input (`Exact`, `Between`, `Greater`, ...), against its
+/// previous value (`Changed`, `Increased`, ...) or both (`IncreasedBy`, `DecreasedBy`).
+#[derive(Debug,Clone,Copy,PartialEq,Eq)]
+pub enum ScanMode{Exact,Unknown,Changed,Unchanged,Increased,IncreasedOrGreater,IncreasedBy,Decreased,DecreasedOrLess,DecreasedBy,Between,Greater,Less,NotEqual}
+impl ScanMode{
+    pub const ALL:&'static [ScanMode]={use ScanMode::*;&[Exact,Unknown,Changed,Unchanged,Increased,IncreasedOrGreater,IncreasedBy,Decreased,DecreasedOrLess,DecreasedBy,Between,Greater,Less,NotEqual]};
+    pub fn name(&self)->&'static str{
+        use ScanMode::*;match self{
+            Exact=>"Exact",Unknown=>"Unknown Initial Value",Changed=>"Changed",Unchanged=>"Unchanged",
+            Increased=>"Increased",IncreasedOrGreater=>"Increased or Greater",IncreasedBy=>"Increased by",
+            Decreased=>"Decreased",DecreasedOrLess=>"Decreased or Less",DecreasedBy=>"Decreased by",
+            Between=>"Between",Greater=>"Greater than",Less=>"Less than",NotEqual=>"Not equal",
+        }
+    }
+    /// Whether the mode needs an input value; `Between` takes a `lo..hi` range.
+    pub fn takes_input(&self)->bool{use ScanMode::*;matches!(self,Exact|IncreasedBy|DecreasedBy|Between|Greater|Less|NotEqual)}
+    /// Whether the mode compares against an earlier value, leaving a first scan nothing to filter on.
+    pub fn needs_old(&self)->bool{use ScanMode::*;!matches!(self,Exact|Between|Greater|Less|NotEqual)}
+}
+
+/// Encoding of a text value in target memory.
+#[derive(Debug,Clone,Copy,PartialEq)]
+pub enum TextEnc{Utf8,Utf16,Ascii}
+impl TextEnc{
+    pub fn unit(&self)->usize{if *self==Self::Utf16{2}else{1}}
+    pub fn encode(&self,s:&str)->Vec<u8>{
+        match self{
+            Self::Utf8=>s.as_bytes().to_vec(),
+            Self::Utf16=>s.encode_utf16().flat_map(u16::to_le_bytes).collect(),
+            Self::Ascii=>s.chars().map(|c|if c.is_ascii(){c as u8}else{b'?'}).collect(),
+        }
+    }
+    pub fn decode(&self,b:&[u8])->String{
+        match self{
+            Self::Utf8=>String::from_utf8_lossy(b).into_owned(),
+            Self::Utf16=>char::decode_utf16(b.chunks_exact(2).map(|c|u16::from_le_bytes([c[0],c[1]]))).map(|r|r.unwrap_or('\u{FFFD}')).collect(),
+            Self::Ascii=>b.iter().map(|&c|c as char).collect(),
+        }
+    }
+    #[inline] fn fold16(u:u16)->u16{
+        char::from_u32(u as u32).map_or(u,|c|{let mut l=c.to_lowercase(); match(l.next(),l.next()){(Some(x),None) if (x as u32)<0x10000=>x as u32 as u16,_=>u}})
+    }
+    /// Compares encoded text, folding case per byte (per UTF-16 unit for `Utf16`) when `nocase`.
+    pub fn eq(&self,nocase:bool,a:&[u8],b:&[u8])->bool{
+        if a.len()!=b.len(){return false;}
+        if !nocase{return a==b;}
+        match self{
+            Self::Utf16=>a.chunks_exact(2).zip(b.chunks_exact(2)).all(|(x,y)|Self::fold16(u16::from_le_bytes([x[0],x[1]]))==Self::fold16(u16::from_le_bytes([y[0],y[1]]))),
+            _=>a.eq_ignore_ascii_case(b),
+        }
+    }
+}
+
 #[derive(Debug,Clone,PartialEq)]
 pub enum ValueType{
-    Int8(i8),Int16(i16),Int32(i32),Int64(i64),
-    UInt8(u8),UInt16(u16),UInt32(u32),UInt64(u64),
+    Int8(i8),Int16(i16),Int32(i32),Int64(i64),Int128(i128),
+    UInt8(u8),UInt16(u16),UInt32(u32),UInt64(u64),UInt128(u128),
+    /// Half-precision float, held widened but always rounded to a value a half can hold.
+    Float16(f32),
     Float32(f32),Float64(f64),
     Size(usize),Pointer(usize),
     Bool(bool),
+    Str{text:String,enc:TextEnc,nocase:bool},
+    /// Byte pattern; a byte matches where `(b&mask)==(bytes&mask)`, so `mask` 0 is a `??` wildcard
+    /// and 0xF0/0x0F wildcard a nibble.
+    Aob{bytes:Vec<u8>,mask:Vec<u8>},
+    /// Values near each other, matched at a base address: a member with an offset sits exactly there,
+    /// one without may be anywhere in the `window` bytes from the base.
+    Group{members:Vec<(ValueType,Option<usize>)>,window:usize},
+    /// A number stored big-endian, as in the guest RAM of PowerPC or MIPS emulators.
+    Be(Box<ValueType>),
+    /// Unsigned field of `len` bits starting at bit `lo` of the little-endian bytes at an address, such
+    /// as a single flag.
+    Bits{value:u64,lo:u32,len:u32},
+    /// A number stored XORed with `key`'s low bytes. An unknown key (`None`) keeps the last known value
+    /// instead, so scans can match how the stored bytes change as the value does.
+    Xor{v:Box<ValueType>,key:Option<u64>},
+    /// A number stored as `value*scale+offset` in `v`'s type, which holds the stored number; fixed-point
+    /// Q formats scale by a power of two.
+    Scaled{v:Box<ValueType>,scale:f64,offset:f64},
+    /// 2 to 4 floats, as Float64 when `double`, such as a position. An input `within` a distance matches
+    /// any vector that close to it.
+    Vector{xs:Vec<f64>,double:bool,within:Option<f64>},
 }
 impl ValueType{
     #[inline] fn r<const N:usize>(b:&[u8])->[u8;N]{let mut a=[0u8;N];let n=b.len().min(N);a[..n].copy_from_slice(&b[..n]);a}
@@ -26,13 +181,72 @@ impl ValueType{
             Self::UInt16(x)=>x.to_ne_bytes().to_vec(),
             Self::Int32(x)=>x.to_ne_bytes().to_vec(),
             Self::UInt32(x)=>x.to_ne_bytes().to_vec(),
+            Self::Float16(x)=>half_bits(x).to_ne_bytes().to_vec(),
             Self::Float32(x)=>x.to_ne_bytes().to_vec(),
+            Self::Int128(x)=>x.to_ne_bytes().to_vec(),
+            Self::UInt128(x)=>x.to_ne_bytes().to_vec(),
             Self::Int64(x)=>x.to_ne_bytes().to_vec(),
             Self::UInt64(x)=>x.to_ne_bytes().to_vec(),
             Self::Float64(x)=>x.to_ne_bytes().to_vec(),
             Self::Size(x)|Self::Pointer(x)=>x.to_ne_bytes().to_vec(),
+            Self::Str{ref text,enc,..}=>enc.encode(text),
+            Self::Aob{ref bytes,..}=>bytes.clone(),
+            Self::Group{ref members,..}=>{
+                let mut b=vec![0;Self::type_size(self)];
+                for (v,o) in members{if let Some(o)=*o{let vb=v.to_bytes(); b[o..o+vb.len()].copy_from_slice(&vb);}}
+                b
+            }
+            Self::Be(ref v)=>{let mut b=v.to_bytes(); b.reverse(); b}
+            Self::Bits{value,lo,..}=>((value as u128)<<lo).to_le_bytes()[..Self::type_size(self)].to_vec(),
+            Self::Xor{ref v,key}=>{let mut b=v.to_bytes(); if let Some(k)=key{Self::xor_key(&mut b,k);} b}
+            Self::Scaled{ref v,..}=>v.to_bytes(),
+            Self::Vector{ref xs,double,..}=>xs.iter().flat_map(|&x|if double{x.to_ne_bytes().to_vec()}else{(x as f32).to_ne_bytes().to_vec()}).collect(),
+        }
+    }
+    #[inline] fn xor_key(b:&mut [u8],key:u64){for (x,k) in b.iter_mut().zip(key.to_le_bytes()){*x^=k;}}
+    #[inline] fn field_mask(len:u32)->u64{if len>=64{u64::MAX}else{(1<<len)-1}}
+    /// Bytes a write must leave untouched are the zero bits of this mask; `None` writes every byte.
+    /// Groups only write their members with a known offset.
+    pub fn write_mask(&self)->Option<Vec<u8>>{
+        match self{
+            Self::Aob{mask,..} if mask.iter().any(|&m|m!=0xFF)=>Some(mask.clone()),
+            Self::Bits{lo,len,..}=>Some(Self::Bits{value:Self::field_mask(*len),lo:*lo,len:*len}.to_bytes()),
+            Self::Group{members,..}=>{
+                let mut m=vec![0;Self::type_size(self)];
+                for (v,o) in members{if let Some(o)=*o{m[o..o+Self::type_size(v)].fill(0xFF);}}
+                Some(m)
+            }
+            _=>None,
         }
     }
+    /// Text, patterns and groups, which are matched on their raw bytes rather than as numbers.
+    #[inline] pub fn is_bytes(&self)->bool{matches!(self,Self::Str{..}|Self::Aob{..}|Self::Group{..}|Self::Xor{key:None,..})}
+    /// Whether the bytes `raw` hold this value: encoded text, a pattern, a group or an equal number.
+    pub fn matches_raw(&self,raw:&[u8],fm:FloatMatch)->bool{
+        match self{
+            Self::Str{text,enc,nocase}=>enc.eq(*nocase,raw,&enc.encode(text)),
+            Self::Aob{bytes,mask}=>raw.len()==bytes.len()&&raw.iter().zip(bytes).zip(mask).all(|((r,b),m)|r&m==b&m),
+            Self::Group{..}=>self.locate(raw,fm).is_some(),
+            _=>raw.len()==Self::type_size(self)&&Self::from_bytes(raw.to_vec(),self.clone()).equals_with(self,fm),
+        }
+    }
+    /// Offset of every group member in `raw`, which starts at the group's base; `None` unless all match.
+    pub fn locate(&self,raw:&[u8],fm:FloatMatch)->Option<Vec<usize>>{
+        let Self::Group{members,..}=self else{return None};
+        members.iter().map(|(v,o)|{
+            let ts=Self::type_size(v);
+            match *o{
+                Some(o)=>(o+ts<=raw.len()&&v.matches_raw(&raw[o..o+ts],fm)).then_some(o),
+                None=>(0..raw.len().saturating_sub(ts)+1).step_by(Self::natural_align(v)).find(|&k|v.matches_raw(&raw[k..k+ts],fm)),
+            }
+        }).collect()
+    }
+    /// Bytes `address_set` writes; `nul` terminates strings with a zero unit.
+    pub fn write_bytes(&self,nul:bool)->Vec<u8>{
+        let mut b=self.to_bytes();
+        if let Self::Str{enc,..}=self{if nul{b.extend(std::iter::repeat(0).take(enc.unit()));}}
+        b
+    }
     pub fn from_bytes(bytes:Vec<u8>,hint:ValueType)->Self{
         match hint{
             Self::Int8(_)=>Self::Int8(i8::from_ne_bytes(Self::r::<1>(&bytes))),
@@ -42,14 +256,49 @@ impl ValueType{
             Self::UInt16(_)=>Self::UInt16(u16::from_ne_bytes(Self::r::<2>(&bytes))),
             Self::Int32(_)=>Self::Int32(i32::from_ne_bytes(Self::r::<4>(&bytes))),
             Self::UInt32(_)=>Self::UInt32(u32::from_ne_bytes(Self::r::<4>(&bytes))),
+            Self::Float16(_)=>Self::Float16(half_value(u16::from_ne_bytes(Self::r::<2>(&bytes)))),
             Self::Float32(_)=>Self::Float32(f32::from_ne_bytes(Self::r::<4>(&bytes))),
+            Self::Int128(_)=>Self::Int128(i128::from_ne_bytes(Self::r::<16>(&bytes))),
+            Self::UInt128(_)=>Self::UInt128(u128::from_ne_bytes(Self::r::<16>(&bytes))),
+            Self::Vector{xs,double,..}=>{
+                let w=if double{8}else{4};
+                let xs=(0..xs.len()).map(|i|{let b=bytes.get(i*w..).unwrap_or(&[]); if double{f64::from_ne_bytes(Self::r::<8>(b))}else{f32::from_ne_bytes(Self::r::<4>(b)) as f64}}).collect();
+                Self::Vector{xs,double,within:None}
+            }
             Self::Int64(_)=>Self::Int64(i64::from_ne_bytes(Self::r::<8>(&bytes))),
             Self::UInt64(_)=>Self::UInt64(u64::from_ne_bytes(Self::r::<8>(&bytes))),
             Self::Float64(_)=>Self::Float64(f64::from_ne_bytes(Self::r::<8>(&bytes))),
             Self::Size(_)=>Self::Size(Self::read_usize(&bytes)),
             Self::Pointer(_)=>Self::Pointer(Self::read_usize(&bytes)),
+            Self::Str{enc,nocase,..}=>Self::Str{text:enc.decode(&bytes),enc,nocase},
+            Self::Aob{..}=>Self::Aob{mask:vec![0xFF;bytes.len()],bytes},
+            Self::Group{..}=>{
+                let Self::Group{members,window}=&hint else{unreachable!()};
+                let at=hint.locate(&bytes,FloatMatch::Epsilon);
+                let members=members.iter().enumerate().map(|(i,(v,o))|{
+                    let o=at.as_ref().map(|a|a[i]).or(*o).filter(|&o|o+Self::type_size(v)<=bytes.len());
+                    (o.map_or_else(||v.clone(),|o|Self::from_bytes(bytes[o..o+Self::type_size(v)].to_vec(),v.clone())),o)
+                }).collect();
+                Self::Group{members,window:*window}
+            }
+            Self::Bits{lo,len,..}=>Self::Bits{value:(u64::from_le_bytes(Self::r::<8>(&bytes))>>lo)&Self::field_mask(len),lo,len},
+            Self::Xor{v,key:Some(k)}=>{let mut b=bytes; Self::xor_key(&mut b,k); Self::Xor{v:Box::new(Self::from_bytes(b,*v)),key:Some(k)}}
+            Self::Xor{v,key:None}=>{
+                // the key that makes these bytes hold the last known value
+                let mut b=bytes; b.resize(8,0); Self::xor_key(&mut b,u64::from_le_bytes(Self::r::<8>(&v.to_bytes())));
+                Self::Xor{v,key:Some(u64::from_le_bytes(Self::r::<8>(&b)))}
+            }
+            Self::Scaled{v,scale,offset}=>Self::Scaled{v:Box::new(Self::from_bytes(bytes,*v)),scale,offset},
+            Self::Be(v)=>{
+                let mut b=bytes; b.resize(Self::type_size(&v),0); b.reverse();
+                Self::Be(Box::new(Self::from_bytes(b,*v)))
+            }
         }
     }
+    /// `v` stored big-endian; single bytes have no order and stay as they are.
+    pub fn big_endian(v:ValueType)->ValueType{if Self::type_size(&v)>1{Self::Be(Box::new(v))}else{v}}
+    /// The value itself, with the byte order dropped.
+    #[inline] fn plain(&self)->&ValueType{match self{Self::Be(v)|Self::Xor{v,..}=>v.plain(),v=>v}}
     pub fn scan_types(v:&ValueType)->Vec<ValueType>{
         use ValueType::*;match v{
             Int8(_)=>vec![Int8(0),Int16(0),Int32(0)],
@@ -60,35 +309,66 @@ impl ValueType{
             UInt16(_)=>vec![UInt16(0),UInt8(0),UInt32(0)],
             UInt32(_)=>vec![UInt32(0),UInt64(0),UInt16(0)],
             UInt64(_)=>vec![UInt64(0),UInt32(0)],
+            Float16(_)=>vec![Float16(0.0)],
             Float32(_)=>vec![Float32(0.0),Float64(0.0)],
+            Int128(_)=>vec![Int128(0)],UInt128(_)=>vec![UInt128(0)],
             Float64(_)=>vec![Float64(0.0),Float32(0.0)],
             Size(_)=>vec![Size(0),UInt64(0),Int64(0)],
             Pointer(_)=>vec![Pointer(0),UInt64(0)],
             Bool(_)=>vec![Bool(false),UInt8(0)],
+            Str{..}|Aob{..}|Group{..}|Bits{..}|Vector{..}=>vec![v.clone()],
+            Be(v)=>Self::scan_types(v).into_iter().map(Self::big_endian).collect(),
+            Xor{key:None,..}|Scaled{..}=>vec![v.clone()],
+            Xor{v,key}=>Self::scan_types(v).into_iter().map(|t|Xor{v:Box::new(t),key:*key}).collect(),
         }
     }
     pub fn type_size(h:&ValueType)->usize{
         use ValueType::*;match h{
             Int8(_)|UInt8(_)|Bool(_)=>1,
-            Int16(_)|UInt16(_)=>2,
+            Int16(_)|UInt16(_)|Float16(_)=>2,
+            Int128(_)|UInt128(_)=>16,
+            Vector{xs,double,..}=>xs.len()*if *double{8}else{4},
             Int32(_)|UInt32(_)|Float32(_)=>4,
             Int64(_)|UInt64(_)|Float64(_)=>8,
             Size(_)|Pointer(_)=>std::mem::size_of::<usize>(),
+            Str{text,enc,..}=>enc.encode(text).len(),
+            Aob{bytes,..}=>bytes.len(),
+            Group{members,window}=>members.iter().map(|(v,o)|o.unwrap_or(0)+Self::type_size(v)).max().unwrap_or(0).max(*window),
+            Be(v)|Xor{v,..}|Scaled{v,..}=>Self::type_size(v),
+            Bits{lo,len,..}=>(lo+len+7) as usize/8,
         }
     }
+    /// Step a scan takes by default: the value's own size, or one code unit for text.
+    pub fn natural_align(h:&ValueType)->usize{match h{ValueType::Str{enc,..}=>enc.unit(),ValueType::Aob{..}|ValueType::Bits{..}=>1,ValueType::Vector{double,..}=>if *double{8}else{4},ValueType::Group{members,..}=>members.first().map_or(1,|m|Self::natural_align(&m.0)),_=>Self::type_size(h)}}
     pub fn type_to_string(t:&ValueType)->&'static str{
         use ValueType::*;match t{
             Int8(_)=>"Int8",Int16(_)=>"Int16",Int32(_)=>"Int32",Int64(_)=>"Int64",
             UInt8(_)=>"UInt8",UInt16(_)=>"UInt16",UInt32(_)=>"UInt32",UInt64(_)=>"UInt64",
             Float32(_)=>"Float32",Float64(_)=>"Float64",
+            Int128(_)=>"Int128",UInt128(_)=>"UInt128",Float16(_)=>"Float16",
+            Vector{xs,double,..}=>match(xs.len(),double){(2,false)=>"Vec2",(3,false)=>"Vec3",(4,false)=>"Vec4",(2,true)=>"DVec2",(3,true)=>"DVec3",_=>"DVec4"},
             Size(_)=>"Size",Pointer(_)=>"Pointer",Bool(_)=>"Bool",
+            Str{enc:TextEnc::Utf8,..}=>"Str",Str{enc:TextEnc::Utf16,..}=>"WStr",Str{enc:TextEnc::Ascii,..}=>"Ascii",Aob{..}=>"AOB",Group{..}=>"Group",
+            Bits{len:1,..}=>"Bit",Bits{..}=>"Bits",Xor{..}=>"Xor",
+            Scaled{scale,offset,..}=>if *offset==0.0&&scale.log2().fract()==0.0{"Fixed"}else{"Scaled"},
+            Be(v)=>match **v{
+                Int16(_)=>"Int16 BE",Int32(_)=>"Int32 BE",Int64(_)=>"Int64 BE",
+                UInt16(_)=>"UInt16 BE",UInt32(_)=>"UInt32 BE",UInt64(_)=>"UInt64 BE",
+                Float32(_)=>"Float32 BE",Float64(_)=>"Float64 BE",Float16(_)=>"Float16 BE",
+                Int128(_)=>"Int128 BE",UInt128(_)=>"UInt128 BE",
+                Size(_)=>"Size BE",Pointer(_)=>"Pointer BE",
+                // single bytes have no order
+                _=>Self::type_to_string(v),
+            },
         }
     }
     pub fn string_to_type(s:&str)->Option<ValueType>{
+        if let Some(t)=s.strip_suffix(" BE"){return Self::string_to_type(t).map(Self::big_endian);}
         use ValueType::*;Some(match s{
             "Int8"=>Int8(0),"Int16"=>Int16(0),"Int32"=>Int32(0),"Int64"=>Int64(0),
             "UInt8"=>UInt8(0),"UInt16"=>UInt16(0),"UInt32"=>UInt32(0),"UInt64"=>UInt64(0),
             "Float32"=>Float32(0.0),"Float64"=>Float64(0.0),
+            "Int128"=>Int128(0),"UInt128"=>UInt128(0),"Float16"=>Float16(0.0),
             "Size"=>Size(0),"Pointer"=>Pointer(0),"Bool"=>Bool(false),
             _=>return None
         })
@@ -96,6 +376,90 @@ impl ValueType{
     #[inline] fn float_eq(a:f32,b:f32)->bool{(a-b).abs()<f32::EPSILON}
     #[inline] fn double_eq(a:f64,b:f64)->bool{(a-b).abs()<f64::EPSILON}
 
+    pub(crate) fn num(&self)->Option<Num>{
+        use ValueType::*;Some(match *self{
+            Int8(x)=>Num::I(x as i128),Int16(x)=>Num::I(x as i128),Int32(x)=>Num::I(x as i128),Int64(x)=>Num::I(x as i128),
+            UInt8(x)=>Num::I(x as i128),UInt16(x)=>Num::I(x as i128),UInt32(x)=>Num::I(x as i128),UInt64(x)=>Num::I(x as i128),
+            Size(x)|Pointer(x)=>Num::I(x as i128),Bool(x)=>Num::I(x as i128),
+            Float32(x)|Float16(x)=>Num::F(x as f64),Float64(x)=>Num::F(x),
+            Int128(x)=>Num::I(x),
+            UInt128(x)=>i128::try_from(x).map_or(Num::F(x as f64),Num::I),
+            Vector{..}=>return None,
+            Str{..}|Aob{..}|Group{..}=>return None,
+            Be(ref v)=>return v.num(),
+            Bits{value,..}=>Num::I(value as i128),
+            Xor{ref v,..}=>return v.num(),
+            Scaled{ref v,scale,offset}=>Num::F((v.num()?.f64()-offset)/scale),
+        })
+    }
+    #[inline] fn f64(&self)->f64{self.num().map_or(f64::NAN,Num::f64)}
+    /// Orders two values of different types by numeric value. Float equality uses the epsilon of the
+    /// narrower float involved, so a Float32 holding 3.14 equals a typed `double:3.14`; a Float16 is
+    /// equal to whatever rounds to the same half.
+    fn cmp_num(&self,o:&ValueType)->Option<Ordering>{
+        let is=|f:fn(&ValueType)->bool|f(self.plain())||f(o.plain());
+        let (half,narrow)=(is(|v|matches!(v,ValueType::Float16(_))),is(|v|matches!(v,ValueType::Float32(_))));
+        match(self.num()?,o.num()?){
+            (Num::I(a),Num::I(b))=>Some(a.cmp(&b)),
+            (a,b)=>{
+                let(a,b)=(a.f64(),b.f64());
+                let eq=if half{half_bits(a as f32)==half_bits(b as f32)}else if narrow{Self::float_eq(a as f32,b as f32)}else{Self::double_eq(a,b)};
+                if eq{Some(Ordering::Equal)}else{a.partial_cmp(&b)}
+            }
+        }
+    }
+    #[inline] fn is_float(&self)->bool{matches!(self.plain(),ValueType::Float16(_)|ValueType::Float32(_)|ValueType::Float64(_)|ValueType::Scaled{..})}
+    /// Equality under `fm` whenever a float is involved, plain `equals` otherwise. Vectors compare each
+    /// component under `fm`, or by distance when `o` has a `within`.
+    pub fn equals_with(&self,o:&ValueType,fm:FloatMatch)->bool{
+        if let (ValueType::Vector{xs:a,..},ValueType::Vector{xs:b,within,..})=(self,o){
+            return a.len()==b.len()&&match within{
+                Some(d)=>a.iter().zip(b).map(|(x,y)|(x-y)*(x-y)).sum::<f64>().sqrt()<=*d,
+                None if fm==FloatMatch::Epsilon=>self.equals(o),
+                None=>a.iter().zip(b).all(|(x,y)|fm.eq(*x,*y,self.precision())),
+            };
+        }
+        if fm!=FloatMatch::Epsilon&&(self.is_float()||o.is_float()){fm.eq(self.f64(),o.f64(),self.precision())}else{self.equals(o)}
+    }
+    /// Relative precision of the float a value is stored as; exact types count as doubles.
+    fn precision(&self)->f64{
+        match self.plain(){
+            ValueType::Float16(_)=>f64::powi(2.0,-10),ValueType::Float32(_)|ValueType::Vector{double:false,..}=>f32::EPSILON as f64,
+            ValueType::Scaled{v,..}=>v.precision(),_=>f64::EPSILON,
+        }
+    }
+    /// Converts to `hint`'s type when the value is representable there; integers may wrap into the
+    /// unsigned range of the same width, floats only convert to integers when they are whole.
+    pub fn cast_to(&self,hint:&ValueType)->Option<ValueType>{
+        use ValueType::*;
+        match hint{
+            Be(h)=>return self.cast_to(h).map(|v|Be(Box::new(v))),
+            Xor{v:h,key}=>return self.cast_to(h).map(|v|Xor{v:Box::new(v),key:*key}),
+            &Scaled{v:ref h,scale,offset}=>{
+                let raw=self.num()?.f64()*scale+offset;
+                return Float64(if h.is_float(){raw}else{raw.round()}).cast_to(h).map(|v|Scaled{v:Box::new(v),scale,offset});
+            }
+            _=>{}
+        }
+        let n=match self.num()?{Num::I(n)=>n,Num::F(f)=>{
+            match hint{Float16(_)=>return Some(Float16(half_round(f as f32))),Float32(_)=>return Some(Float32(f as f32)),Float64(_)=>return Some(Float64(f)),_=>{}}
+            if f.fract()!=0.0||!f.is_finite(){return None;} f as i128
+        }};
+        let bits=(Self::type_size(hint)*8) as u32;
+        let fits=|| -(1i128<<(bits-1))<=n&&n<(1i128<<bits);
+        Some(match hint{
+            Float16(_)=>Float16(half_round(n as f32)),Float32(_)=>Float32(n as f32),Float64(_)=>Float64(n as f64),
+            Bool(_)=>Bool(n!=0),
+            Int128(_)=>Int128(n),UInt128(_)=>UInt128(n as u128),
+            Str{..}|Aob{..}|Group{..}|Be(_)|Xor{..}|Scaled{..}|Vector{..}=>return None,
+            &Bits{lo,len,..}=>if (0..=Self::field_mask(len) as i128).contains(&n){Bits{value:n as u64,lo,len}}else{return None},
+            _ if !fits()=>return None,
+            Int8(_)=>Int8(n as i8),Int16(_)=>Int16(n as i16),Int32(_)=>Int32(n as i32),Int64(_)=>Int64(n as i64),
+            UInt8(_)=>UInt8(n as u8),UInt16(_)=>UInt16(n as u16),UInt32(_)=>UInt32(n as u32),UInt64(_)=>UInt64(n as u64),
+            Size(_)=>Size(n as usize),Pointer(_)=>Pointer(n as usize),
+        })
+    }
+
     pub fn equals(&self,o:&ValueType)->bool{
         use ValueType::*;
         match(self,o){
@@ -110,9 +474,14 @@ impl ValueType{
             (UInt64(a),UInt64(b))=>a==b,
             (Float32(a),Float32(b))=>Self::float_eq(*a,*b),
             (Float64(a),Float64(b))=>Self::double_eq(*a,*b),
+            (Int128(a),Int128(b))=>a==b,
+            (UInt128(a),UInt128(b))=>a==b,
+            (Vector{xs:a,double,..},Vector{xs:b,..})=>a.len()==b.len()&&a.iter().zip(b).all(|(x,y)|if *double{Self::double_eq(*x,*y)}else{Self::float_eq(*x as f32,*y as f32)}),
             (Size(a),Size(b))=>a==b,
             (Pointer(a),Pointer(b))=>a==b,
-            _=>false
+            (Str{text:a,enc,nocase},Str{text:b,enc:eb,nocase:nb})=>enc==eb&&enc.eq(*nocase||*nb,&enc.encode(a),&enc.encode(b)),
+            (Aob{bytes:a,mask:ma},Aob{bytes:b,mask:mb})=>a.len()==b.len()&&(0..a.len()).all(|i|{let m=ma[i]&mb[i]; a[i]&m==b[i]&m}),
+            _=>self.cmp_num(o)==Some(Ordering::Equal)
         }
     }
     pub fn greater(&self,o:&ValueType)->bool{
@@ -128,10 +497,12 @@ impl ValueType{
             (UInt64(a),UInt64(b))=>a>b,
             (Float32(a),Float32(b))=>!Self::float_eq(*a,*b)&&a>b,
             (Float64(a),Float64(b))=>!Self::double_eq(*a,*b)&&a>b,
+            (Int128(a),Int128(b))=>a>b,
+            (UInt128(a),UInt128(b))=>a>b,
             (Size(a),Size(b))=>a>b,
             (Pointer(a),Pointer(b))=>a>b,
             (Bool(a),Bool(b))=>*a&&!*b,
-            _=>false
+            _=>self.cmp_num(o)==Some(Ordering::Greater)
         }
     }
     pub fn less(&self,o:&ValueType)->bool{
@@ -147,10 +518,12 @@ impl ValueType{
             (UInt64(a),UInt64(b))=>a<b,
             (Float32(a),Float32(b))=>!Self::float_eq(*a,*b)&&a<b,
             (Float64(a),Float64(b))=>!Self::double_eq(*a,*b)&&a<b,
+            (Int128(a),Int128(b))=>a<b,
+            (UInt128(a),UInt128(b))=>a<b,
             (Size(a),Size(b))=>a<b,
             (Pointer(a),Pointer(b))=>a<b,
             (Bool(a),Bool(b))=>!*a&&*b,
-            _=>false
+            _=>self.cmp_num(o)==Some(Ordering::Less)
         }
     }
     pub fn add(&self,o:&ValueType)->Option<ValueType>{
@@ -165,10 +538,17 @@ impl ValueType{
             (UInt64(a),UInt64(b))=>UInt64(a.wrapping_add(*b)),
             (Float32(a),Float32(b))=>Float32(a+b),
             (Float64(a),Float64(b))=>Float64(a+b),
-            (Size(a),Size(b))=>Size(a+b),
-            (Pointer(a),Pointer(b))=>Pointer(a+b),
+            (Float16(a),Float16(b))=>Float16(half_round(a+b)),
+            (Int128(a),Int128(b))=>Int128(a.wrapping_add(*b)),
+            (UInt128(a),UInt128(b))=>UInt128(a.wrapping_add(*b)),
+            (Size(a),Size(b))=>Size(a.wrapping_add(*b)),
+            (Pointer(a),Pointer(b))=>Pointer(a.wrapping_add(*b)),
             (Bool(a),Bool(b))=>Bool(*a||*b),
-            _=>return None
+            (Be(a),Be(b))=>Be(Box::new(a.add(b)?)),
+            (&Bits{value:a,lo,len},Bits{value:b,..})=>Bits{value:a.wrapping_add(*b)&Self::field_mask(len),lo,len},
+            (Xor{v:a,key},Xor{v:b,..})=>Xor{v:Box::new(a.add(b)?),key:*key},
+            (Scaled{..},Scaled{..})=>return Float64(self.f64()+o.f64()).cast_to(self),
+            _=>return o.cast_to(self).and_then(|c|self.add(&c))
         })
     }
     pub fn sub(&self,o:&ValueType)->Option<ValueType>{
@@ -183,25 +563,153 @@ impl ValueType{
             (UInt64(a),UInt64(b))=>UInt64(a.wrapping_sub(*b)),
             (Float32(a),Float32(b))=>Float32(a-b),
             (Float64(a),Float64(b))=>Float64(a-b),
-            (Size(a),Size(b))=>Size(a-b),
-            (Pointer(a),Pointer(b))=>Pointer(a-b),
+            (Float16(a),Float16(b))=>Float16(half_round(a-b)),
+            (Int128(a),Int128(b))=>Int128(a.wrapping_sub(*b)),
+            (UInt128(a),UInt128(b))=>UInt128(a.wrapping_sub(*b)),
+            (Size(a),Size(b))=>Size(a.wrapping_sub(*b)),
+            (Pointer(a),Pointer(b))=>Pointer(a.wrapping_sub(*b)),
             (Bool(a),Bool(b))=>Bool(*a&&!*b),
-            _=>return None
+            (Be(a),Be(b))=>Be(Box::new(a.sub(b)?)),
+            (&Bits{value:a,lo,len},Bits{value:b,..})=>Bits{value:a.wrapping_sub(*b)&Self::field_mask(len),lo,len},
+            (Xor{v:a,key},Xor{v:b,..})=>Xor{v:Box::new(a.sub(b)?),key:*key},
+            (Scaled{..},Scaled{..})=>return Float64(self.f64()-o.f64()).cast_to(self),
+            _=>return o.cast_to(self).and_then(|c|self.sub(&c))
         })
     }
-    pub fn comparator(mode:&str,old:&ValueType,new:&ValueType,inp:&ValueType)->bool{
-        match mode{
-            "Exact"=>new.equals(inp),
-            "Changed"=>!new.equals(old),
-            "Unchanged"=>new.equals(old),
-            "Increased"=>new.greater(old),
-            "Increased or Greater"=>new.greater(old)||new.equals(old),
-            "Increased by"=>old.add(inp).map_or(false,|v|new.equals(&v)),
-            "Decreased"=>new.less(old),
-            "Decreased or Less"=>new.less(old)||new.equals(old),
-            "Decreased by"=>old.sub(inp).map_or(false,|v|new.equals(&v)),
-            _=>false
+    /// Inclusive range test, compared numerically like `equals`. NaN, which is neither less nor greater
+    /// than anything, and values that are not numbers are never inside.
+    pub fn between(&self,lo:&ValueType,hi:&ValueType)->bool{!self.f64().is_nan()&&!self.less(lo)&&!self.greater(hi)}
+    /// `hi` is the upper bound for Between, where `inp` is the lower one. `fm` applies to Exact and to
+    /// the float step of Increased by/Decreased by.
+    /// Whether `new` passes `mode`; modes that take an input fail without one.
+    pub fn comparator(mode:ScanMode,old:&ValueType,new:&ValueType,inp:Option<&ValueType>,hi:Option<&ValueType>,fm:FloatMatch)->bool{
+        use ScanMode::*;
+        let by=|d:f64,i:&ValueType|fm!=FloatMatch::Epsilon&&(new.is_float()||i.is_float())&&fm.eq(d,i.f64(),new.precision());
+        match (mode,inp){
+            (Exact,Some(i))=>new.equals_with(i,fm),
+            (NotEqual,Some(i))=>!new.equals_with(i,fm),
+            (Greater,Some(i))=>new.greater(i),
+            (Less,Some(i))=>new.less(i),
+            (Between,Some(i))=>hi.is_some_and(|h|new.between(i,h)),
+            (Unknown,_)=>true,
+            (Changed,_)=>!new.equals(old),
+            (Unchanged,_)=>new.equals(old),
+            (Increased,_)=>new.greater(old),
+            (IncreasedOrGreater,_)=>new.greater(old)||new.equals(old),
+            (IncreasedBy,Some(i))=>by(new.f64()-old.f64(),i)||old.add(i).is_some_and(|v|new.equals(&v)),
+            (Decreased,_)=>new.less(old),
+            (DecreasedOrLess,_)=>new.less(old)||new.equals(old),
+            (DecreasedBy,Some(i))=>by(old.f64()-new.f64(),i)||old.sub(i).is_some_and(|v|new.equals(&v)),
+            _=>false,
+        }
+    }
+    /// Parses `lo..hi`, with an optional type prefix applied to both bounds (`float:0.5..1.5`).
+    pub fn parse_user_range(input:&str)->Option<(ValueType,ValueType)>{
+        let t=input.trim();
+        let (pre,body)=match t.rsplit_once(':'){Some((p,b))=>(format!("{p}:"),b),None=>(String::new(),t)};
+        let (lo,hi)=body.split_once("..")?;
+        Some((Self::parse_user_value(&format!("{pre}{}",lo.trim()))?,Self::parse_user_value(&format!("{pre}{}",hi.trim()))?))
+    }
+    fn text(v:&str,enc:TextEnc,nocase:bool)->Option<ValueType>{(!v.is_empty()).then(||ValueType::Str{text:v.to_string(),enc,nocase})}
+    /// Parses `48 8B ?? 4? ?5` into bytes and a mask; tokens may also run together (`488B??`).
+    fn aob(v:&str)->Option<ValueType>{
+        let (mut bytes,mut mask)=(vec![],vec![]);
+        for tok in v.split_whitespace(){
+            let tok=if tok=="?"{"??"}else{tok};
+            if tok.len()%2!=0{return None;}
+            for p in tok.as_bytes().chunks(2){
+                let (mut b,mut m)=(0u8,0u8);
+                for &c in p{
+                    let (d,dm)=if c==b'?'{(0,0)}else{((c as char).to_digit(16)? as u8,0xF)};
+                    b=b<<4|d; m=m<<4|dm;
+                }
+                bytes.push(b); mask.push(m);
+            }
+        }
+        (!bytes.is_empty()).then_some(ValueType::Aob{bytes,mask})
+    }
+    /// Parses `i32:100, i32:50@8, i8:7, window:64`: members are separated by commas, `@off` pins a
+    /// member to an offset from the base (the first member defaults to 0) and `window:n` bounds the rest.
+    /// A group spans at most `MAX_GROUP` bytes, and only bases with the whole span inside a region are tried.
+    fn group(v:&str)->Option<ValueType>{
+        let (mut members,mut window)=(vec![],0);
+        for tok in v.split(',').map(str::trim).filter(|t|!t.is_empty()){
+            if let Some(w)=tok.strip_prefix("window:"){window=w.trim().parse().ok().filter(|&w|w<=MAX_GROUP)?; continue;}
+            let (val,off)=match tok.rsplit_once('@'){
+                Some((v,o))=>{let o=o.trim(); (v,Some(if let Some(h)=o.strip_prefix("0x"){usize::from_str_radix(h,16).ok()?}else{o.parse().ok()?}))}
+                None=>(tok,members.is_empty().then_some(0)),
+            };
+            let val=Self::parse_user_value(val)?;
+            if matches!(val,ValueType::Group{..}){return None;}
+            if off.unwrap_or(0).checked_add(Self::type_size(&val)).map_or(true,|e|e>MAX_GROUP){return None;}
+            members.push((val,off));
         }
+        (!members.is_empty()).then_some(ValueType::Group{members,window})
+    }
+    /// Parses `3`, `3:1`, `4..7` or `4..7:5`: one bit or an inclusive bit range, and the field value
+    /// (0 if left out) in decimal, `0x` hex or `0b` binary.
+    fn bits(v:&str)->Option<ValueType>{
+        let (range,val)=v.split_once(':').unwrap_or((v,"0"));
+        let (lo,hi)=match range.split_once(".."){Some((a,b))=>(a.trim().parse::<u32>().ok()?,b.trim().parse::<u32>().ok()?),None=>{let b=range.trim().parse().ok()?; (b,b)}};
+        if hi<lo||hi>=64{return None;}
+        let val=val.trim();
+        let value=if let Some(h)=val.strip_prefix("0x"){u64::from_str_radix(h,16).ok()?}else if let Some(b)=val.strip_prefix("0b"){u64::from_str_radix(b,2).ok()?}else{val.parse().ok()?};
+        let len=hi-lo+1;
+        (value<=Self::field_mask(len)).then_some(ValueType::Bits{value,lo,len})
+    }
+    /// Parses `0x1F:i32:100`: the key in hex or decimal, `?` when unknown, then the value.
+    fn xor(v:&str)->Option<ValueType>{
+        let (key,val)=v.split_once(':')?;
+        let key=match key.trim(){"?"=>None,k=>Some(if let Some(h)=k.strip_prefix("0x"){u64::from_str_radix(h,16).ok()?}else{k.parse().ok()?})};
+        let val=Self::parse_user_value(val).filter(|v|v.num().is_some())?;
+        Some(ValueType::Xor{v:Box::new(val),key})
+    }
+    /// `value` stored as `value*scale+offset` in `hint`'s type, rounded for integers.
+    fn scaled(hint:ValueType,scale:f64,offset:f64,value:&str)->Option<ValueType>{
+        if scale==0.0||!scale.is_finite(){return None;}
+        let x:f64=value.trim().parse().ok()?;
+        ValueType::Float64(x).cast_to(&ValueType::Scaled{v:Box::new(hint),scale,offset})
+    }
+    /// Parses `100:i32:12.5` or `100,5:i32:12.5`: scale, optional offset, stored type (Int32 if left
+    /// out) and the value.
+    fn scale(v:&str)->Option<ValueType>{
+        let (spec,rest)=v.split_once(':')?;
+        let (scale,offset)=match spec.split_once(','){Some((s,o))=>(s.trim().parse().ok()?,o.trim().parse().ok()?),None=>(spec.trim().parse().ok()?,0.0)};
+        let (hint,val)=match rest.rsplit_once(':'){
+            Some((t,val))=>(Self::parse_user_value(&format!("{t}:0")).filter(|h|h.num().is_some()&&!matches!(h,ValueType::Bool(_)|ValueType::Bits{..}|ValueType::Xor{..}|ValueType::Scaled{..}))?,val),
+            None=>(ValueType::Int32(0),rest),
+        };
+        Self::scaled(hint,scale,offset,val)
+    }
+    /// Parses the `q16.16`/`uq8.8` of a Q format: integer and fraction bits, summing to a type's width.
+    fn fixed(q:&str,v:&str)->Option<ValueType>{
+        let (signed,q)=match q.strip_prefix("uq"){Some(q)=>(false,q),None=>(true,q.strip_prefix('q')?)};
+        let (m,n)=q.split_once('.')?;
+        let n:u32=n.parse().ok()?;
+        use ValueType::*;
+        let hint=match (m.parse::<u32>().ok()?+n,signed){
+            (8,true)=>Int8(0),(16,true)=>Int16(0),(32,true)=>Int32(0),(64,true)=>Int64(0),
+            (8,false)=>UInt8(0),(16,false)=>UInt16(0),(32,false)=>UInt32(0),(64,false)=>UInt64(0),
+            _=>return None,
+        };
+        Self::scaled(hint,2f64.powi(n as i32),0.0,v)
+    }
+    /// Parses `10, 20, 30` (parentheses optional) with `n` components, and a match distance after `~`
+    /// or `within`: `10,20,30 ~1.5`.
+    fn vector(n:usize,double:bool,v:&str)->Option<ValueType>{
+        let (xs,within)=match v.split_once('~').or_else(||v.split_once("within")){Some((x,d))=>(x,Some(d.trim().parse::<f64>().ok().filter(|d|*d>=0.0)?)),None=>(v,None)};
+        let xs=xs.trim().trim_start_matches('(').trim_end_matches(')').split(',').map(|x|x.trim().parse::<f64>().ok()).collect::<Option<Vec<f64>>>()?;
+        let xs=if double{xs}else{xs.into_iter().map(|x|x as f32 as f64).collect()};
+        (xs.len()==n).then_some(ValueType::Vector{xs,double,within})
+    }
+    /// `parse_user_value` for Set and Lock, which cannot encode an XOR value whose key is unknown.
+    pub fn parse_write_value(input:&str)->Result<ValueType,String>{
+        let v=Self::parse_user_value(input).ok_or("Bad value")?;
+        let unknown=|v:&ValueType|matches!(v,Self::Xor{key:None,..});
+        if unknown(&v)||matches!(&v,Self::Group{members,..} if members.iter().any(|m|unknown(&m.0))){
+            return Err("XOR key unknown, write with xor:<key>:… using the key shown in the results".into());
+        }
+        Ok(v)
     }
     pub fn parse_user_value(input:&str)->Option<ValueType>{
         let t=input.trim();
@@ -215,10 +723,88 @@ impl ValueType{
             Some(("int64"|"i64"|"long",v))=>v.parse().ok().map(ValueType::Int64),
             Some(("float32"|"f32"|"float",v))=>v.parse().ok().map(ValueType::Float32),
             Some(("float64"|"f64"|"double",v))=>v.parse().ok().map(ValueType::Float64),
+            Some(("float16"|"f16"|"half",v))=>v.parse().ok().map(|f|ValueType::Float16(half_round(f))),
+            Some(("int128"|"i128",v))=>v.parse().ok().map(ValueType::Int128),
+            Some(("uint128"|"u128",v))=>v.parse().ok().map(ValueType::UInt128),
+            Some(("vec2",v))=>Self::vector(2,false,v),Some(("vec3",v))=>Self::vector(3,false,v),Some(("vec4",v))=>Self::vector(4,false,v),
+            Some(("dvec2",v))=>Self::vector(2,true,v),Some(("dvec3",v))=>Self::vector(3,true,v),Some(("dvec4",v))=>Self::vector(4,true,v),
             Some(("size"|"s",v))=>v.parse().ok().map(ValueType::Size),
             Some(("ptr"|"pointer",v))=>v.parse().ok().map(ValueType::Pointer),
+            Some(("str"|"utf8",v))=>Self::text(v,TextEnc::Utf8,false),
+            Some(("istr",v))=>Self::text(v,TextEnc::Utf8,true),
+            Some(("wstr"|"utf16",v))=>Self::text(v,TextEnc::Utf16,false),
+            Some(("iwstr",v))=>Self::text(v,TextEnc::Utf16,true),
+            Some(("ascii",v))=>Self::text(v,TextEnc::Ascii,false),
+            Some(("iascii",v))=>Self::text(v,TextEnc::Ascii,true),
+            Some(("aob",v))=>Self::aob(v),
+            Some(("group",v))=>Self::group(v),
+            Some(("bit"|"bits",v))=>Self::bits(v),
+            Some(("xor",v))=>Self::xor(v),
+            Some(("scale",v))=>Self::scale(v),
+            Some((q,v)) if q.starts_with('q')||q.starts_with("uq")=>Self::fixed(q,v),
+            // kept wrapped even when it fits a byte, so `be:100` still scans the wider types big-endian
+            Some(("be"|"bigendian",v))=>Self::parse_user_value(v).filter(|v|v.num().is_some()&&!matches!(v,ValueType::Be(_))).map(|v|ValueType::Be(Box::new(v))),
             _=>t.parse::<i64>().ok().map(|n|if (i8::MIN as i64..=i8::MAX as i64).contains(&n){ValueType::Int8(n as i8)}else if (i16::MIN as i64..=i16::MAX as i64).contains(&n){ValueType::Int16(n as i16)}else if (i32::MIN as i64..=i32::MAX as i64).contains(&n){ValueType::Int32(n as i32)}else{ValueType::Int64(n)})
                 .or_else(||t.parse::<f64>().ok().map(|n|if n.abs()<=(f32::MAX as f64){ValueType::Float32(n as f32)}else{ValueType::Float64(n)}))
         }
     }
 }
+
+impl std::fmt::Display for ValueType{
+    fn fmt(&self,f:&mut std::fmt::Formatter)->std::fmt::Result{
+        use ValueType::*;match self{
+            Int8(x)=>write!(f,"{x}"),Int16(x)=>write!(f,"{x}"),Int32(x)=>write!(f,"{x}"),Int64(x)=>write!(f,"{x}"),
+            UInt8(x)=>write!(f,"{x}"),UInt16(x)=>write!(f,"{x}"),UInt32(x)=>write!(f,"{x}"),UInt64(x)=>write!(f,"{x}"),
+            Float32(x)|Float16(x)=>write!(f,"{x}"),Float64(x)=>write!(f,"{x}"),Int128(x)=>write!(f,"{x}"),UInt128(x)=>write!(f,"{x}"),
+            Vector{xs,within,..}=>{
+                write!(f,"(")?;
+                for (i,x) in xs.iter().enumerate(){if i>0{write!(f,", ")?;} write!(f,"{x}")?;}
+                write!(f,")")?;
+                if let Some(d)=within{write!(f," ~{d}")?;}
+                Ok(())
+            }
+            Size(x)=>write!(f,"{x}"),Pointer(x)=>write!(f,"0x{x:x}"),Bool(x)=>write!(f,"{x}"),Be(v)=>write!(f,"{v}"),
+            Xor{v,key:Some(k)}=>write!(f,"{v} (key 0x{k:x})"),Xor{v,key:None}=>write!(f,"{v} (key ?)"),
+            Scaled{..}=>write!(f,"{}",self.f64()),
+            Bits{value,len,..}=>if *len==1{write!(f,"{value}")}else{write!(f,"{value} (0b{value:0w$b})",w=*len as usize)},
+            Str{text,..}=>write!(f,"{text:?}"),
+            Aob{bytes,mask}=>{
+                for (i,(b,m)) in bytes.iter().zip(mask).enumerate(){
+                    if i>0{write!(f," ")?;}
+                    for sh in [4,0]{if m>>sh&0xF==0{write!(f,"?")?;}else{write!(f,"{:X}",b>>sh&0xF)?;}}
+                }
+                Ok(())
+            }
+            Group{members,..}=>{
+                for (i,(v,o)) in members.iter().enumerate(){
+                    if i>0{write!(f,", ")?;}
+                    write!(f,"{} {v}",Self::type_to_string(v))?;
+                    if let Some(o)=o{write!(f,"@{o}")?;}
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Bits of the half nearest to `f`, ties to even.
+fn half_bits(f:f32)->u16{
+    let x=f.to_bits(); let sign=((x>>16)&0x8000) as u16;
+    let (exp,man)=(((x>>23)&0xFF) as i32,x&0x7F_FFFF);
+    if exp==0xFF{return sign|0x7C00|if man!=0{0x200}else{0};}
+    let e=exp-127+15;
+    if e>=0x1F{return sign|0x7C00;}
+    let (mut h,rem,half)=if e<=0{
+        if e< -10{return sign;}
+        let (m,shift)=(man|0x80_0000,(14-e) as u32);
+        (m>>shift,m&((1<<shift)-1),1<<(shift-1))
+    }else{(((e as u32)<<10)|(man>>13),man&0x1FFF,0x1000)};
+    // a carry out of the mantissa bumps the exponent, up to infinity
+    if rem>half||(rem==half&&h&1==1){h+=1;}
+    sign|h as u16
+}
+fn half_value(h:u16)->f32{
+    let (sign,e,m)=(if h&0x8000!=0{-1.0}else{1.0},(h>>10)&0x1F,(h&0x3FF) as f32);
+    sign*match e{0=>m*2f32.powi(-24),0x1F=>if m==0.0{f32::INFINITY}else{f32::NAN},_=>(1.0+m/1024.0)*2f32.powi(e as i32-15)}
+}
+#[inline] fn half_round(f:f32)->f32{half_value(half_bits(f))}
@@ -1,10 +1,24 @@
 use eframe::{egui, App, Frame};
-use std::{collections::HashMap, error::Error, fs, io::Read, sync::{Arc, Mutex}};
-use crate::types::{ValueType, RegionGroup};
-use crate::scan::MemoryScanner;
+use std::{error::Error, fs, io::Read, sync::{Arc, Mutex}, thread::{self, JoinHandle}};
+use crate::cond::Cond;
+use crate::expr::Expr;
+use crate::types::{FloatMatch, MemoryRegion, RegionFilter, ScanMode, ValueType, RegionGroup};
+use crate::mem::{Backend, BACKENDS};
+use crate::pointer::{self, PointerChain, PointerMap, PointerParams};
+use crate::scan::{MemoryScanner, ScanParams};
+use crate::store::Scan;
 
-const TYPES:&[&str]=&["Auto","Int8","Int16","Int32","Int64","UInt8","UInt16","UInt32","UInt64","Float32","Float64","Pointer","Size","Bool"];
-const MODES:&[&str]=&["Exact","Changed","Unchanged","Increased","Increased or Greater","Increased by","Decreased","Decreased or Less","Decreased by"];
+const TYPES:&[&str]=&["Auto","Int8","Int16","Int32","Int64","UInt8","UInt16","UInt32","UInt64","Float32","Float64","Pointer","Size","Bool","Int128","UInt128","Float16",
+    "Int16 BE","Int32 BE","Int64 BE","UInt16 BE","UInt32 BE","UInt64 BE","Float32 BE","Float64 BE","Int128 BE","UInt128 BE","Float16 BE"];
+const ALIGNS:&[(Option<usize>,&str)]=&[(None,"Natural"),(Some(1),"1"),(Some(2),"2"),(Some(4),"4"),(Some(8),"8")];
+const FLOATS:&[&str]=&["Epsilon","Rounded","Truncated","Absolute ±","Relative ±"];
+/// Pointer jobs yield the chains and, for a fresh scan, the pointer map they were found in.
+type PointerJob=JoinHandle<Result<(Vec<PointerChain>,Option<PointerMap>),String>>;
+
+/// How the scan inputs are read: one mode on the value box, a compound condition in the value box, or
+/// an expression with the value box as its `input`.
+#[derive(Clone,Copy,PartialEq)]
+enum Mode{Scan(ScanMode),Cond,Expr}
 
 pub struct Smem{
     scanner:Arc<Mutex<MemoryScanner>>,
@@ -12,15 +26,34 @@ pub struct Smem{
     err:Option<String>,
     zoom:f32,
     selected_region:Option<String>,
+    region_filter:RegionFilter,
+    regions_open:bool,
+    range_lo:String,
+    range_hi:String,
+    resident_only:bool,
     scan_value:String,
-    scan_mode:String,
+    scan_mode:Mode,
+    expr:String,
     sel_type:Option<String>,
-    scan_history:Vec<HashMap<usize,ValueType>>,
-    scan_types_history:Vec<HashMap<usize,(ValueType,String)>>,
-    scan_results:Vec<usize>,
+    align:Option<usize>,
+    float_mode:String,
+    float_tol:String,
+    nul:bool,
+    history:Vec<(Arc<Scan>,String)>,
+    reference:Option<usize>,
     pid_query:String,
     pid_items:Vec<(i32,String)>,
     pid_selected:Option<i32>,
+    job:Option<JoinHandle<Result<(Scan,String),String>>>,
+    ptr_open:bool,
+    ptr_target:String,
+    ptr_depth:usize,
+    ptr_max_off:String,
+    ptr_sort:usize,
+    chains:Vec<PointerChain>,
+    ptr_map:Option<PointerMap>,
+    ptr_file:String,
+    ptr_job:Option<PointerJob>,
 }
 impl Smem{
     pub fn new(pid:i32)->Self{
@@ -31,15 +64,34 @@ impl Smem{
             err:None,
             zoom:1.0,
             selected_region:None,
+            region_filter:RegionFilter::default(),
+            regions_open:false,
+            range_lo:String::new(),
+            range_hi:String::new(),
+            resident_only:false,
             scan_value:"0".into(),
-            scan_mode:"Exact".into(),
+            scan_mode:Mode::Scan(ScanMode::Exact),
+            expr:String::new(),
             sel_type:None,
-            scan_history:vec![],
-            scan_types_history:vec![],
-            scan_results:vec![],
+            align:None,
+            float_mode:"Epsilon".into(),
+            float_tol:"0.01".into(),
+            nul:true,
+            history:vec![],
+            reference:None,
             pid_query:String::new(),
             pid_items:vec![],
             pid_selected:None,
+            job:None,
+            ptr_open:false,
+            ptr_target:String::new(),
+            ptr_depth:4,
+            ptr_max_off:"0x1000".into(),
+            ptr_sort:0,
+            chains:vec![],
+            ptr_map:None,
+            ptr_file:"smem.ptrmap".into(),
+            ptr_job:None,
         };
         let _=this.init();
         this
@@ -58,6 +110,21 @@ impl Smem{
     #[inline] fn color(b:u8)->egui::Color32{egui::Color32::from_gray((b as f32*0.8) as u8)}
     #[inline] fn ipx(x:f32)->f32{x.round().max(1.0)}
     #[inline] fn ibox(x:f32,y:f32,w:f32,h:f32)->egui::Rect{let(x,y,w,h)=(x.floor(),y.floor(),w.floor(),h.floor()); egui::Rect::from_min_size(egui::pos2(x,y),egui::vec2(w.max(1.0),h.max(1.0)))}
+    fn float_match(&self)->Result<FloatMatch,String>{
+        let tol=||self.float_tol.trim().parse::<f64>().ok().filter(|t|*t>=0.0).ok_or_else(||format!("Bad tolerance {}",self.float_tol));
+        Ok(match self.float_mode.as_str(){
+            "Rounded"=>FloatMatch::Rounded(0),"Truncated"=>FloatMatch::Truncated(0),
+            "Absolute ±"=>FloatMatch::Abs(tol()?),"Relative ±"=>FloatMatch::Rel(tol()?),
+            _=>FloatMatch::Epsilon,
+        })
+    }
+    /// The expression box compiled with the value box, if filled in, as its `input`.
+    fn compile_expr(&self)->Result<Expr,String>{
+        let v=self.scan_value.trim();
+        let input=if v.is_empty(){None}else{Some(ValueType::parse_user_value(v).ok_or_else(||format!("Failed to parse input {v:?}"))?)};
+        Expr::compile(&self.expr,input)
+    }
+    #[inline] fn parse_addr(s:&str)->Option<usize>{let s=s.trim(); usize::from_str_radix(s.strip_prefix("0x").unwrap_or(s),16).ok()}
     #[inline] fn chosen_types(&self)->Option<Vec<ValueType>>{self.sel_type.as_deref().and_then(ValueType::string_to_type).map(|v|vec![v])}
 
     fn refresh_pids(&mut self){
@@ -82,10 +149,9 @@ impl Smem{
             s.set_pid(pid);
             self.groups=s.load_maps().unwrap_or_default();
             let _=s.attach();
-            s.reset_scan();
-            self.scan_history.clear();
-            self.scan_types_history.clear();
-            self.scan_results.clear();
+            self.job=None;
+            self.history.clear();
+            self.reference=None;
         }
     }
 
@@ -97,41 +163,92 @@ impl Smem{
     }
 
     fn scan(&mut self){
-        let tf=self.chosen_types();
-        let r=if self.scan_history.is_empty(){
-            self.scanner.lock().unwrap().first_scan(
-                &self.groups,
-                &self.selected_region,
-                &self.scan_value,
-                &self.scan_mode,
-                tf.as_deref(),
-                &mut self.scan_history,
-                &mut self.scan_types_history,
-                &mut self.scan_results
-            )
+        if self.job.is_some(){return;}
+        let mem=match self.scanner.lock().unwrap().mem(){Ok(m)=>m,Err(e)=>{self.err=Some(e.to_string()); return;}};
+        let float=match self.float_match(){Ok(f)=>f,Err(e)=>{self.err=Some(e); return;}};
+        let cond=match self.scan_mode{Mode::Scan(m)=>Cond::mode(m,&self.scan_value,float),Mode::Cond=>Cond::parse(&self.scan_value,float),Mode::Expr=>self.compile_expr().map(Cond::Expr)};
+        let cond=match cond{Ok(c)=>c,Err(e)=>{self.err=Some(e); return;}};
+        let range=match self.scan_range(){Ok(r)=>r,Err(e)=>{self.err=Some(e); return;}};
+        let p=ScanParams{cond,types:self.chosen_types(),align:self.align};
+        self.job=Some(if let Some(prev)=self.history.last().map(|h|h.0.clone()){
+            let reference=self.reference.and_then(|i|self.history.get(i).map(|h|(h.0.clone(),if i==0{"first scan".to_string()}else{format!("scan {}",i+1)})));
+            let label=match &reference{Some((_,n))=>format!("{} since {n}",p.cond),None=>p.cond.to_string()};
+            let first=self.history.first().map(|h|h.0.clone());
+            thread::spawn(move||MemoryScanner::next_scan(mem,&prev,reference.as_ref().map(|r|&*r.0),first.as_deref(),&p).map(|s|(s,label)))
         }else{
-            self.scanner.lock().unwrap().next_scan(
-                &self.scan_value,
-                &self.scan_mode,
-                tf.as_deref(),
-                &mut self.scan_history,
-                &mut self.scan_types_history,
-                &mut self.scan_results
-            )
-        };
-        if let Err(e)=r{self.err=Some(e);}
+            let regions=MemoryScanner::scan_regions(&self.groups,&self.selected_region,&self.region_filter,range);
+            let (label,pid,resident)=(p.cond.to_string(),self.scanner.lock().unwrap().pid,self.resident_only);
+            thread::spawn(move||{
+                let regions=if resident{MemoryScanner::resident_runs(pid,&regions).map_err(|e|format!("pagemap: {e}"))?}else{regions};
+                MemoryScanner::first_scan(mem,regions,&p).map(|s|(s,label))
+            })
+        });
+    }
+
+    fn poll_scan(&mut self){
+        if !self.job.as_ref().is_some_and(|j|j.is_finished()){return;}
+        match self.job.take().unwrap().join(){
+            Ok(Ok((r,label)))=>self.history.push((Arc::new(r),label)),
+            Ok(Err(e))=>self.err=Some(e),
+            Err(_)=>self.err=Some("scan thread panicked".into()),
+        }
+    }
+
+    fn pointer_scan(&mut self){
+        if self.ptr_job.is_some(){return;}
+        let mem=match self.scanner.lock().unwrap().mem(){Ok(m)=>m,Err(e)=>{self.err=Some(e.to_string()); return;}};
+        let (Some(target),Some(max_off))=(Self::parse_addr(&self.ptr_target),Self::parse_addr(&self.ptr_max_off)) else{self.err=Some("Bad target or max offset".into()); return};
+        let (groups,p)=(self.groups.clone(),PointerParams{target,depth:self.ptr_depth,max_off});
+        self.ptr_job=Some(thread::spawn(move||pointer::pointer_scan(mem,&groups,&p).map(|(c,m)|(c,Some(m)))));
+    }
+    /// Drops the chains that no longer lead to the target, e.g. after the target restarted and its new
+    /// address was entered.
+    fn pointer_recheck(&mut self){
+        if self.ptr_job.is_some(){return;}
+        let mem=match self.scanner.lock().unwrap().mem(){Ok(m)=>m,Err(e)=>{self.err=Some(e.to_string()); return;}};
+        let Some(target)=Self::parse_addr(&self.ptr_target) else{self.err=Some("Bad target".into()); return};
+        let (groups,chains)=(self.groups.clone(),self.chains.clone());
+        self.ptr_job=Some(thread::spawn(move||Ok((pointer::recheck(&mem,&groups,&chains,target),None))));
+    }
+    /// Keeps the chains that resolve to their run's target in every map listed in `ptr_file` (`;`-separated).
+    fn pointer_filter(&mut self){
+        if self.ptr_job.is_some(){return;}
+        let (paths,chains)=(self.ptr_file.split(';').map(|p|p.trim().to_string()).filter(|p|!p.is_empty()).collect::<Vec<_>>(),self.chains.clone());
+        self.ptr_job=Some(thread::spawn(move||{
+            let maps=paths.iter().map(|p|PointerMap::load(p).map_err(|e|format!("{p}: {e}"))).collect::<Result<Vec<_>,_>>()?;
+            Ok((pointer::intersect(&chains,&maps),None))
+        }));
+    }
+    fn poll_pointers(&mut self){
+        if !self.ptr_job.as_ref().is_some_and(|j|j.is_finished()){return;}
+        match self.ptr_job.take().unwrap().join(){
+            Ok(Ok((c,m)))=>{self.chains=c; self.sort_chains(); if m.is_some(){self.ptr_map=m;}}
+            Ok(Err(e))=>self.err=Some(e),
+            Err(_)=>self.err=Some("pointer scan thread panicked".into()),
+        }
+    }
+    fn sort_chains(&mut self){
+        match self.ptr_sort{
+            1=>self.chains.sort_by_key(|c|c.offsets.len()),
+            2=>self.chains.sort_by_key(|c|c.offsets.last().copied()),
+            _=>self.chains.sort_by(|a,b|(&a.module,a.base_off,&a.offsets).cmp(&(&b.module,b.base_off,&b.offsets))),
+        }
     }
 
-    fn previous_scan(&mut self){self.scanner.lock().unwrap().previous_scan();}
+    fn previous_scan(&mut self){
+        if self.history.len()>1{self.history.pop();}
+        if self.reference.is_some_and(|i|i+1>=self.history.len()){self.reference=None;}
+    }
     fn reset_scan(&mut self){
-        self.scanner.lock().unwrap().reset_scan();
-        self.scan_history.clear();
-        self.scan_types_history.clear();
-        self.scan_results.clear();
+        self.job=None;
+        self.history.clear();
+        self.reference=None;
     }
+    #[inline] fn latest(&self)->Option<&Scan>{self.history.last().map(|h|&*h.0)}
+    fn result_addrs(&self)->Vec<usize>{self.latest().and_then(|s|s.hits()).map(|r|r.addresses()).unwrap_or_default()}
 
     fn topbar(&mut self,ctx:&egui::Context){
-        let (pid,attached)={{let s=self.scanner.lock().unwrap();(s.pid,s.is_attached)}};
+        let (pid,attached,backend,active)={{let s=self.scanner.lock().unwrap();(s.pid,s.is_attached,s.backend,s.mem().ok().map(|m|m.backend()))}};
         egui::TopBottomPanel::top("top")
             .frame(egui::Frame::default().inner_margin(egui::Margin::symmetric(6.0,6.0)))
             .show(ctx,|ui|{
@@ -139,25 +256,55 @@ impl Smem{
                     ui.horizontal_wrapped(|ui|{
                         ui.strong(format!("PID {pid}"));
                         if ui.button("Detach").clicked(){self.scanner.lock().unwrap().detach();}
-                        if ui.button("Reload Maps").clicked(){if let Ok(v)=self.scanner.lock().unwrap().load_maps(){self.groups=v;}}
+                        if ui.button("Reload Maps").clicked(){self.reload_maps();}
+                        if ui.selectable_label(self.regions_open,"Regions").clicked(){self.regions_open^=true;}
+                        if ui.selectable_label(self.ptr_open,"Pointers").clicked(){
+                            self.ptr_open^=true;
+                            if self.ptr_target.is_empty(){if let Some(a)=self.result_addrs().first(){self.ptr_target=format!("0x{a:x}");}}
+                        }
+                        self.backend_combo(ui,backend,active);
                         ui.separator();
                         let mut t=self.sel_type.clone().unwrap_or_else(||"Auto".into());
                         egui::ComboBox::from_id_source("type").selected_text(&t).width(120.0).show_ui(ui,|ui|{
                             for &v in TYPES{if ui.selectable_label(t==v,v).clicked(){t=v.to_string();}}
                         });
                         self.sel_type=if t=="Auto"{None}else{Some(t)};
-                        egui::ComboBox::from_id_source("mode").selected_text(&self.scan_mode).width(200.0).show_ui(ui,|ui|{
-                            for &m in MODES{ui.selectable_value(&mut self.scan_mode,m.to_string(),m);}
+                        let an=ALIGNS.iter().find(|a|a.0==self.align).map_or("Natural",|a|a.1);
+                        egui::ComboBox::from_id_source("align").selected_text(format!("Align {an}")).width(100.0).show_ui(ui,|ui|{
+                            for &(a,n) in ALIGNS{ui.selectable_value(&mut self.align,a,n);}
                         });
-                        ui.add(egui::TextEdit::singleline(&mut self.scan_value).hint_text("value").desired_width(160.0));
-                        if ui.button("Scan").clicked(){self.scan();}
-                        if ui.button("Prev").clicked(){self.previous_scan();}
+                        let mode=match self.scan_mode{Mode::Scan(m)=>m.name(),Mode::Cond=>"Condition",Mode::Expr=>"Expression"};
+                        egui::ComboBox::from_id_source("mode").selected_text(mode).width(200.0).show_ui(ui,|ui|{
+                            for &m in ScanMode::ALL{ui.selectable_value(&mut self.scan_mode,Mode::Scan(m),m.name());}
+                            ui.selectable_value(&mut self.scan_mode,Mode::Cond,"Condition").on_hover_text("Combine modes, e.g. changed AND > 100 AND < 5000");
+                            ui.selectable_value(&mut self.scan_mode,Mode::Expr,"Expression").on_hover_text("Keep values where an expression over new, old, first, input and addr holds, e.g. new - old == 3 && new % 2 == 0");
+                        });
+                        egui::ComboBox::from_id_source("float").selected_text(&self.float_mode).width(100.0).show_ui(ui,|ui|{
+                            for &f in FLOATS{ui.selectable_value(&mut self.float_mode,f.to_string(),f);}
+                        });
+                        if self.float_mode.ends_with('±'){ui.add(egui::TextEdit::singleline(&mut self.float_tol).hint_text("tolerance").desired_width(60.0));}
+                        if self.scan_mode==Mode::Expr{ui.add(egui::TextEdit::singleline(&mut self.expr).hint_text("new > first * 2").desired_width(220.0));}
+                        let hint=match self.scan_mode{Mode::Scan(ScanMode::Between)=>"lo..hi",Mode::Cond=>"changed AND > 100",Mode::Expr=>"input",_=>"value, str:text or aob:48 8B ?? ??"};
+                        ui.add(egui::TextEdit::singleline(&mut self.scan_value).hint_text(hint).desired_width(160.0));
+                        let bad=if self.scan_mode==Mode::Expr{self.compile_expr().err()}else{None};
+                        if let Some(e)=&bad{ui.colored_label(egui::Color32::RED,e);}
+                        let idle=self.job.is_none();
+                        if ui.add_enabled(idle&&bad.is_none(),egui::Button::new("Scan")).clicked(){self.scan();}
+                        if ui.add_enabled(idle,egui::Button::new("Prev")).clicked(){self.previous_scan();}
                         if ui.button("Reset").clicked(){self.reset_scan();}
+                        if !idle{ui.spinner(); ui.label("Scanning…");}
+                        else if let Some(s)=self.latest(){ui.label(match s{Scan::Hits(r)=>format!("{} results",r.len()),Scan::Snap(_)=>format!("snapshot, {} candidates",s.len())});}
                         ui.separator();
                         ui.add(egui::Slider::new(&mut self.zoom,0.5..=16.0).logarithmic(true).text("Zoom"));
                         ui.separator();
-                        if ui.button("Set").clicked(){if let Err(e)=self.scanner.lock().unwrap().address_set(&self.scan_value,&mut self.scan_results){self.err=Some(e);}}
-                        if ui.button("Lock").clicked(){MemoryScanner::address_set_lock(self.scanner.clone(),self.scan_value.clone());}
+                        if ui.button("Set").clicked(){let a=self.result_addrs(); if let Err(e)=self.scanner.lock().unwrap().address_set(&self.scan_value,&a,self.nul){self.err=Some(e);}}
+                        if ui.button("Lock").clicked(){
+                            match ValueType::parse_write_value(&self.scan_value){
+                                Ok(v)=>MemoryScanner::address_set_lock(self.scanner.clone(),v,self.nul,self.result_addrs()),
+                                Err(e)=>self.err=Some(e),
+                            }
+                        }
+                        ui.checkbox(&mut self.nul,"NUL").on_hover_text("Terminate written text with a zero");
                         if let Some(e)=self.err.take(){ui.colored_label(egui::Color32::RED,e);}
                     });
                 }else{
@@ -179,63 +326,265 @@ impl Smem{
                             }
                         });
                         if ui.button("Attach").clicked(){self.attach_selected();}
+                        self.backend_combo(ui,backend,active);
                         if let Some(e)=self.err.take(){ui.colored_label(egui::Color32::RED,e);}
                     });
                 }
             });
     }
 
-    fn tooltip(&self,ctx:&egui::Context,id:egui::Id,row:usize,rect:egui::Rect,resp:&egui::Response,buf:&[u8],bsz:f32){
+    fn backend_combo(&mut self,ui:&mut egui::Ui,cur:Backend,active:Option<Backend>){
+        let txt=match active{Some(a) if a!=cur=>format!("{} ({})",cur.name(),a.name()),_=>cur.name().to_string()};
+        egui::ComboBox::from_id_source("backend").selected_text(txt).width(200.0).show_ui(ui,|ui|{
+            for &b in BACKENDS{
+                if ui.selectable_label(cur==b,b.name()).clicked()&&b!=cur{
+                    if let Err(e)=self.scanner.lock().unwrap().set_backend(b){self.err=Some(e.to_string());}
+                }
+            }
+        });
+    }
+
+    /// Scan history; picking an entry makes the next scan compare against it instead of the latest one.
+    fn history_panel(&mut self,ctx:&egui::Context){
+        if self.history.is_empty(){return;}
+        egui::SidePanel::left("history").resizable(true).default_width(220.0).show(ctx,|ui|{
+            ui.strong("Compare with");
+            if ui.selectable_label(self.reference.is_none(),"Previous scan").clicked(){self.reference=None;}
+            ui.separator();
+            egui::ScrollArea::vertical().auto_shrink([false;2]).show(ui,|ui|{
+                let last=self.history.len()-1;
+                for (i,(s,label)) in self.history.iter().enumerate(){
+                    let txt=format!("#{} {label}  ({})",i+1,s.len());
+                    if i==last{ui.weak(format!("{txt}  current"));}
+                    else if ui.selectable_label(self.reference==Some(i),txt).clicked(){self.reference=Some(i);}
+                }
+            });
+        });
+    }
+
+    fn tooltip(&self,ctx:&egui::Context,row:usize,rect:egui::Rect,resp:&egui::Response,buf:&[u8],bsz:f32){
         if !resp.hovered(){return;}
-        egui::show_tooltip(ctx,id,|ui|{
+        egui::show_tooltip(ctx,egui::Id::new(row),|ui|{
             ui.monospace(format!("0x{row:016X}"));
             if let Some(pos)=resp.hover_pos(){
                 let col=((pos.x-rect.min.x)/bsz).floor() as usize;
                 if col<buf.len(){
                     let addr=row+col;
-                    if let Some((_,tstr))=self.scan_types_history.last().and_then(|m|m.get(&addr)){
-                        if let Some(t)=ValueType::string_to_type(tstr){
-                            let sz=ValueType::type_size(&t);
-                            if col+sz<=buf.len(){
-                                let v=ValueType::from_bytes(buf[col..col+sz].to_vec(),t.clone());
-                                ui.monospace(format!("{v:?}"));
-                                return;
-                            }
+                    let (mut bytes,mut field)=(&buf[col..=col],vec![]);
+                    match self.latest().and_then(|s|s.hits()).and_then(|r|r.get(addr)){
+                        Some((c,_)) if col+c.width<=buf.len()=>{
+                            let v=ValueType::from_bytes(buf[col..col+c.width].to_vec(),c.ty.clone());
+                            ui.monospace(format!("{} {v}",ValueType::type_to_string(&c.ty)));
+                            if matches!(c.ty,ValueType::Bits{..}){bytes=&buf[col..col+c.width]; field=c.ty.write_mask().unwrap_or_default();}
                         }
+                        _=>{ui.monospace(format!("hex 0x{:02X}  dec {}",buf[col],buf[col]));}
                     }
-                    ui.monospace(format!("hex 0x{:02X}  dec {}",buf[col],buf[col]));
+                    Self::bit_grid(ui,bytes,&field);
                 }
             }
         });
     }
 
+    /// Bits 7..0 of each byte in `bytes`, one row per byte offset, with the bits set in the matching
+    /// byte of `field` highlighted.
+    fn bit_grid(ui:&mut egui::Ui,bytes:&[u8],field:&[u8]){
+        egui::Grid::new("bits").spacing([6.0,0.0]).show(ui,|ui|{
+            ui.label("");
+            for i in (0..8).rev(){ui.weak(egui::RichText::new(i.to_string()).monospace());}
+            ui.end_row();
+            for (k,&b) in bytes.iter().enumerate(){
+                let m=field.get(k).copied().unwrap_or(0);
+                ui.weak(egui::RichText::new(format!("+{k}")).monospace());
+                for i in (0..8).rev(){
+                    let t=egui::RichText::new(if b>>i&1==1{"1"}else{"0"}).monospace();
+                    ui.label(if m>>i&1==1{t.strong().color(egui::Color32::YELLOW)}else{t});
+                }
+                ui.end_row();
+            }
+        });
+    }
+
     fn results_view(&mut self,ui:&mut egui::Ui){
-        let latest=self.scan_types_history.last().unwrap();
+        let Some(latest)=self.latest().and_then(|s|s.hits()) else{return};
         let row_h=Self::ipx((6.0*self.zoom).clamp(4.0,24.0));
         let full_w=Self::ipx(ui.available_width());
-        egui::ScrollArea::vertical().auto_shrink([false;2]).show(ui,|ui|{
-            let mut addrs:Vec<_>=latest.keys().cloned().collect(); addrs.sort_unstable();
-            for addr in addrs{
-                let sz=ValueType::type_size(&latest[&addr].0);
+        let Ok(mem)=self.scanner.lock().unwrap().mem() else{return};
+        egui::ScrollArea::vertical().auto_shrink([false;2]).show_rows(ui,row_h,latest.len(),|ui,range|{
+            let rows:Vec<_>=range.filter_map(|i|latest.row(i)).collect();
+            let mut bufs:Vec<Vec<u8>>=rows.iter().map(|(c,_)|vec![0;c.width]).collect();
+            let ok=mem.read_many(&mut rows.iter().map(|(c,i)|c.addrs[*i]).zip(bufs.iter_mut().map(|b|b.as_mut_slice())).collect::<Vec<_>>());
+            for ((&(c,i),buf),ok) in rows.iter().zip(&bufs).zip(ok){
+                let (addr,tname)=(c.addrs[i],ValueType::type_to_string(&c.ty));
                 let (r,resp)=ui.allocate_exact_size(egui::vec2(full_w,row_h),egui::Sense::click());
                 let rect=Self::ibox(r.min.x,r.min.y,full_w,row_h);
-                if let Ok(buf)=self.scanner.lock().unwrap().read_memory(addr,sz){
+                if ok{
                     ui.painter().rect_filled(rect,0.0,Self::color(buf[0]));
-                    ui.painter().text(rect.left_top()+egui::vec2(6.0,0.0),egui::Align2::LEFT_TOP,format!("0x{addr:016x}  [{}]",sz),egui::FontId::monospace(11.0),egui::Color32::LIGHT_GRAY);
+                    let v=ValueType::from_bytes(buf.clone(),c.ty.clone());
+                    let label=if c.align==ValueType::natural_align(&c.ty){format!("0x{addr:016x}  [{tname}]  {v}")}else{format!("0x{addr:016x}  [{tname} align {}]  {v}",c.align)};
+                    ui.painter().text(rect.left_top()+egui::vec2(6.0,0.0),egui::Align2::LEFT_TOP,label,egui::FontId::monospace(11.0),egui::Color32::LIGHT_GRAY);
                     if resp.clicked_by(egui::PointerButton::Secondary){ui.ctx().output_mut(|o|o.copied_text=format!("0x{:x}",addr));}
-                    self.tooltip(ui.ctx(),egui::Id::new(addr),addr,rect,&resp,&buf,row_h);
+                    self.tooltip(ui.ctx(),addr,rect,&resp,buf,row_h);
                 }
             }
         });
     }
 
+    fn pointer_window(&mut self,ctx:&egui::Context){
+        let mut open=self.ptr_open;
+        egui::Window::new("Pointer scan").open(&mut open).default_width(560.0).show(ctx,|ui|{
+            ui.horizontal(|ui|{
+                ui.label("Target"); ui.add(egui::TextEdit::singleline(&mut self.ptr_target).hint_text("0x…").desired_width(140.0));
+                ui.label("Depth"); ui.add(egui::DragValue::new(&mut self.ptr_depth).clamp_range(1..=8));
+                ui.label("Max offset"); ui.add(egui::TextEdit::singleline(&mut self.ptr_max_off).desired_width(70.0));
+            });
+            ui.horizontal(|ui|{
+                let idle=self.ptr_job.is_none();
+                if ui.add_enabled(idle,egui::Button::new("Scan")).clicked(){self.pointer_scan();}
+                if ui.add_enabled(idle&&!self.chains.is_empty(),egui::Button::new("Recheck")).clicked(){self.pointer_recheck();}
+                if !idle{ui.spinner();}else{ui.label(format!("{} chains",self.chains.len()));}
+            });
+            ui.horizontal(|ui|{
+                let idle=self.ptr_job.is_none();
+                ui.label("Map file"); ui.add(egui::TextEdit::singleline(&mut self.ptr_file).hint_text("run1.ptrmap;run2.ptrmap").desired_width(220.0));
+                if ui.add_enabled(self.ptr_map.is_some(),egui::Button::new("Save map")).on_hover_text("Save the last scan's pointers and module bases").clicked(){
+                    if let Some(Err(e))=self.ptr_map.as_ref().map(|m|m.save(self.ptr_file.trim())){self.err=Some(format!("{}: {e}",self.ptr_file.trim()));}
+                }
+                if ui.add_enabled(idle&&!self.chains.is_empty(),egui::Button::new("Filter by maps")).on_hover_text("Keep chains that reach the target in every listed map").clicked(){self.pointer_filter();}
+            });
+            ui.horizontal(|ui|{
+                ui.label("Sort");
+                for (k,n) in ["Base","Depth","Last offset"].into_iter().enumerate(){
+                    if ui.selectable_label(self.ptr_sort==k,n).clicked(){self.ptr_sort=k; self.sort_chains();}
+                }
+            });
+            ui.separator();
+            let (Ok(mem),target)=(self.scanner.lock().unwrap().mem(),Self::parse_addr(&self.ptr_target)) else{return};
+            let mods=pointer::modules(&self.groups);
+            let row_h=ui.text_style_height(&egui::TextStyle::Monospace);
+            egui::ScrollArea::vertical().auto_shrink([false;2]).show_rows(ui,row_h,self.chains.len(),|ui,range|{
+                for c in &self.chains[range]{
+                    ui.horizontal(|ui|{
+                        let (txt,col)=match c.resolve(&mem,&mods){
+                            Some(a)=>(format!("0x{a:x}"),if Some(a)==target{egui::Color32::LIGHT_GREEN}else{egui::Color32::LIGHT_RED}),
+                            None=>("??".into(),egui::Color32::GRAY),
+                        };
+                        ui.add(egui::Label::new(egui::RichText::new(format!("{:<16}",txt)).monospace().color(col)));
+                        let r=ui.add(egui::Label::new(egui::RichText::new(c.to_string()).monospace()).sense(egui::Sense::click()));
+                        if r.clicked_by(egui::PointerButton::Secondary){ui.ctx().output_mut(|o|o.copied_text=c.to_string());}
+                    });
+                }
+            });
+        });
+        self.ptr_open=open;
+    }
+
+    /// Re-reads the maps, keeping which groups and regions are enabled.
+    fn reload_maps(&mut self){
+        let Ok(mut v)=self.scanner.lock().unwrap().load_maps() else{return};
+        for g in &mut v{
+            let Some(o)=self.groups.iter().find(|o|o.name==g.name) else{continue};
+            g.enabled=o.enabled;
+            for r in &mut g.regions{if let Some(x)=o.regions.iter().find(|x|x.start==r.start){r.enabled=x.enabled;}}
+        }
+        self.groups=v;
+    }
+    /// The custom address range, `None` when both ends are empty.
+    fn scan_range(&self)->Result<Option<(usize,usize)>,String>{
+        if self.range_lo.trim().is_empty()&&self.range_hi.trim().is_empty(){return Ok(None);}
+        let lo=if self.range_lo.trim().is_empty(){Some(0)}else{Self::parse_addr(&self.range_lo)};
+        let hi=if self.range_hi.trim().is_empty(){Some(usize::MAX)}else{Self::parse_addr(&self.range_hi)};
+        match (lo,hi){(Some(lo),Some(hi)) if lo<hi=>Ok(Some((lo,hi))),_=>Err("Bad address range".into())}
+    }
+    /// Enables the groups `keep` picks with all their regions, and scans them through `filter`.
+    fn preset(&mut self,keep:impl Fn(&RegionGroup)->bool,filter:RegionFilter){
+        for g in &mut self.groups{g.enabled=keep(g); for r in &mut g.regions{r.enabled=true;}}
+        self.selected_region=None; self.region_filter=filter;
+    }
+    fn human(n:usize)->String{
+        const UNITS:[&str;5]=["B","KiB","MiB","GiB","TiB"];
+        let (mut v,mut u)=(n as f64,0);
+        while v>=1024.0&&u<UNITS.len()-1{v/=1024.0; u+=1;}
+        if u==0{format!("{n} B")}else{format!("{v:.1} {}",UNITS[u])}
+    }
+
+    /// Groups and regions a first scan covers, with presets, kind filters and an address range.
+    fn region_window(&mut self,ctx:&egui::Context){
+        let mut open=self.regions_open;
+        egui::Window::new("Regions").open(&mut open).default_width(640.0).show(ctx,|ui|{
+            ui.horizontal(|ui|{
+                ui.label("Presets");
+                if ui.button("All").clicked(){self.preset(|_|true,RegionFilter::default());}
+                if ui.button("Heap only").clicked(){self.preset(|g|g.name=="[heap]",RegionFilter::default());}
+                if ui.button("Main module only").clicked(){
+                    let exe=self.scanner.lock().unwrap().exe_path();
+                    self.preset(|g|exe.as_ref()==Some(&g.name),RegionFilter::default());
+                }
+                if ui.button("Anonymous rw").clicked(){self.preset(|_|true,RegionFilter::ANON_RW);}
+            });
+            ui.horizontal(|ui|{
+                self.filter_menu(ui);
+                ui.label("Range");
+                ui.add(egui::TextEdit::singleline(&mut self.range_lo).hint_text("start").desired_width(120.0));
+                ui.label("–");
+                ui.add(egui::TextEdit::singleline(&mut self.range_hi).hint_text("end").desired_width(120.0));
+                if ui.button("Clear").clicked(){self.range_lo.clear(); self.range_hi.clear();}
+                ui.checkbox(&mut self.resident_only,"Resident only").on_hover_text("Skip pages that are not in RAM, per /proc/pid/pagemap");
+                if ui.button("Reload").on_hover_text("Reload maps and memory stats").clicked(){self.reload_maps();}
+            });
+            let range=self.scan_range();
+            if let Err(e)=&range{ui.colored_label(egui::Color32::RED,e);}
+            let sel=MemoryScanner::scan_regions(&self.groups,&self.selected_region,&self.region_filter,range.ok().flatten());
+            let total:usize=self.groups.iter().flat_map(|g|&g.regions).map(MemoryRegion::size).sum();
+            let rss:usize=sel.iter().map(|r|r.rss).sum();
+            ui.label(format!("Scanning {} regions, {} of {}, {} resident",sel.len(),Self::human(sel.iter().map(MemoryRegion::size).sum()),Self::human(total),Self::human(rss)));
+            ui.separator();
+            egui::ScrollArea::vertical().max_height(480.0).auto_shrink([false,true]).show(ui,|ui|{
+                for g in &mut self.groups{
+                    let on:usize=g.regions.iter().filter(|r|r.enabled).map(MemoryRegion::size).sum();
+                    let all:usize=g.regions.iter().map(MemoryRegion::size).sum();
+                    let rss:usize=g.regions.iter().map(|r|r.rss).sum();
+                    let id=ui.make_persistent_id(("region",&g.name));
+                    egui::collapsing_header::CollapsingState::load_with_default_open(ui.ctx(),id,false).show_header(ui,|ui|{
+                        ui.checkbox(&mut g.enabled,"");
+                        let solo=self.selected_region.as_ref()==Some(&g.name);
+                        if ui.selectable_label(solo,"only").on_hover_text("Scan only this group").clicked(){self.selected_region=if solo{None}else{Some(g.name.clone())};}
+                        ui.label(format!("{}  {} / {}  rss {}",g.name,Self::human(on),Self::human(all),Self::human(rss)));
+                    }).body(|ui|{
+                        for r in &mut g.regions{
+                            let (p,(maj,min))=(r.perms,r.dev);
+                            let t=format!("{:x}-{:x}  {:>10}  rss {:>10}  {p}  off {:x}  dev {maj:02x}:{min:02x}  inode {}",r.start,r.end,Self::human(r.size()),Self::human(r.rss),r.offset,r.inode);
+                            ui.checkbox(&mut r.enabled,egui::RichText::new(t).monospace().small())
+                                .on_hover_text(format!("Rss {}\nSwap {}\nAnonymous {}",Self::human(r.rss),Self::human(r.swap),Self::human(r.anon)));
+                        }
+                    });
+                }
+            });
+        });
+        self.regions_open=open;
+    }
+
+    /// Which kinds of regions a first scan covers.
+    fn filter_menu(&mut self,ui:&mut egui::Ui){
+        let f=&mut self.region_filter;
+        ui.menu_button(format!("Regions: {f}"),|ui|{
+            for (name,o,y,n) in [("Write",&mut f.write,"writable","read-only"),("Exec",&mut f.exec,"exec","no exec"),("Sharing",&mut f.shared,"shared","private"),("Backing",&mut f.file,"file","anonymous")]{
+                ui.horizontal(|ui|{ui.label(name); ui.radio_value(o,None,"any"); ui.radio_value(o,Some(true),y); ui.radio_value(o,Some(false),n);});
+            }
+            ui.horizontal(|ui|{
+                if ui.button("rw-p only").clicked(){*f=RegionFilter::RW_PRIVATE;}
+                if ui.button("All").clicked(){*f=RegionFilter::default();}
+            });
+        });
+    }
+
     fn maps_view(&mut self,ui:&mut egui::Ui){
         egui::ScrollArea::vertical().drag_to_scroll(true).auto_shrink([false;2]).show(ui,|ui|{
             for g in self.groups.iter().filter(|g|g.enabled){
                 ui.collapsing(&g.name,|ui|{
                     for region in &g.regions{
-                        let size=region.end.saturating_sub(region.start); if size==0{continue;}
-                        ui.small(format!("{:x}-{:x}  {} bytes",region.start,region.end,size));
+                        let size=region.size(); if size==0{continue;}
+                        let (p,(maj,min))=(region.perms,region.dev);
+                        ui.small(format!("{:x}-{:x}  {size} bytes  rss {}  swap {}  {p}  off {:x}  dev {maj:02x}:{min:02x}  inode {}",region.start,region.end,Self::human(region.rss),Self::human(region.swap),region.offset,region.inode));
                         let bsz=Self::ipx((6.0*self.zoom).clamp(3.0,18.0));
                         let avail_w=Self::ipx(ui.available_width());
                         let bpr=(avail_w/bsz).floor() as usize; if bpr==0{continue;}
@@ -253,7 +602,7 @@ impl Smem{
                                     p.rect_filled(Self::ibox(x,rect.min.y,bsz,bsz),0.0,Self::color(b));
                                 }
                                 if resp.clicked_by(egui::PointerButton::Secondary){ui.ctx().output_mut(|o|o.copied_text=format!("0x{:x}",start));}
-                                self.tooltip(ui.ctx(),egui::Id::new(start),start,rect,&resp,&buf,bsz);
+                                self.tooltip(ui.ctx(),start,rect,&resp,&buf,bsz);
                             }
                         }
                     }
@@ -266,10 +615,16 @@ impl Smem{
 impl App for Smem{
     fn update(&mut self,ctx:&egui::Context,_:&mut Frame){
         ctx.request_repaint_after(std::time::Duration::from_millis(100));
+        self.poll_scan();
+        self.poll_pointers();
         self.topbar(ctx);
+        self.pointer_window(ctx);
+        self.region_window(ctx);
+        if !ctx.wants_keyboard_input()&&ctx.input(|i|i.key_pressed(egui::Key::R)){self.regions_open^=true;}
+        self.history_panel(ctx);
         egui::CentralPanel::default().show(ctx,|ui|{
             if let Some(e)=&self.err{ui.colored_label(egui::Color32::RED,e); return;}
-            if !self.scan_types_history.is_empty() && !self.scan_results.is_empty(){self.results_view(ui);}else{self.maps_view(ui);}
+            if self.latest().and_then(|s|s.hits()).is_some_and(|r|!r.is_empty()){self.results_view(ui);}else{self.maps_view(ui);}
         });
     }
 }